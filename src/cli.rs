@@ -0,0 +1,270 @@
+use crate::diagnostics::{ErrorFormat, Reporter};
+use crate::error::Error;
+use crate::formatter;
+use crate::syntax_trees::export::AstFormat;
+use crate::{check, dump_ast, dump_tokens, read_source, run_prompt, Backend, Session};
+use std::fs;
+use std::io;
+
+/// What the binary was asked to do, parsed from its arguments
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cli {
+    pub command: Command,
+    pub backend: Backend,
+    pub format: ErrorFormat,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Command {
+    /// Anything after the script is left for the script itself, which sees it as `args`
+    Run {
+        script: Script,
+        args: Vec<String>,
+    },
+    #[default]
+    Repl,
+    Check(Script),
+    Tokens(Script),
+    Ast {
+        script: Script,
+        format: AstFormat,
+    },
+    /// Files are rewritten in place, code from stdin or `-e` is printed
+    Fmt {
+        script: Script,
+        check: bool,
+    },
+}
+
+/// Where the source comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Script {
+    /// A path, or `-` for stdin
+    File(String),
+    /// Code given straight on the command line with `-e`
+    Code(String),
+}
+
+impl Cli {
+    /// Parses everything after the program name. Options can go before or after the command,
+    /// but once `run` has its script the rest belong to the script, dashes and all
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Error> {
+        let mut cli = Self::default();
+        let mut name = None;
+        let mut script = None;
+        let mut rest = Vec::new();
+        let mut check = false;
+        let mut ast_format = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if script.is_some() && matches!(name.as_deref(), None | Some("run")) {
+                rest.push(arg);
+                rest.extend(args.by_ref());
+                break;
+            }
+            match arg.as_str() {
+                "--vm" => cli.backend = Backend::Vm,
+                "--check" => check = true,
+                "--json" => ast_format = Some(AstFormat::Json),
+                "--sexpr" => ast_format = Some(AstFormat::Sexpr),
+                "-e" if script.is_none() => {
+                    script = Some(Script::Code(args.next().ok_or(Error::Usage)?))
+                }
+                "-" if script.is_none() => script = Some(Script::File(arg)),
+                _ if arg.starts_with("--error-format=") => {
+                    cli.format = arg["--error-format=".len()..].parse()?
+                }
+                _ if arg.starts_with('-') => return Err(Error::Usage),
+                "run" | "repl" | "check" | "tokens" | "ast" | "fmt"
+                    if name.is_none() && script.is_none() =>
+                {
+                    name = Some(arg)
+                }
+                _ if script.is_none() => script = Some(Script::File(arg)),
+                _ => return Err(Error::Usage),
+            }
+        }
+        cli.command = match (name.as_deref(), script) {
+            (None, None) | (Some("repl"), None) => Command::Repl,
+            (None | Some("run"), Some(script)) => Command::Run { script, args: rest },
+            (Some("check"), Some(script)) => Command::Check(script),
+            (Some("tokens"), Some(script)) => Command::Tokens(script),
+            (Some("ast"), Some(script)) => Command::Ast {
+                script,
+                format: ast_format.take().unwrap_or_default(),
+            },
+            (Some("fmt"), Some(script)) => Command::Fmt { script, check },
+            _ => return Err(Error::Usage),
+        };
+        // --check only means something to fmt, and --json and --sexpr only to ast
+        if ast_format.is_some() || check && !matches!(cli.command, Command::Fmt { .. }) {
+            return Err(Error::Usage);
+        }
+        Ok(cli)
+    }
+
+    /// Does what was asked. Errors in the script are reported here, the ones returned are only
+    /// passed back for their exit code, apart from usage, io and formatting check errors which
+    /// are left to the caller
+    pub fn run(&self) -> Result<(), Error> {
+        let script = match &self.command {
+            Command::Repl => return run_prompt(self.backend, self.format),
+            Command::Run { script, .. }
+            | Command::Check(script)
+            | Command::Tokens(script)
+            | Command::Ast { script, .. }
+            | Command::Fmt { script, .. } => script,
+        };
+        let (source, reporter) = self.read(script)?;
+        let result = match &self.command {
+            Command::Run { args, .. } => {
+                let mut session = Session::new(self.backend);
+                session.define_args(args);
+                return session.run(&source, &reporter);
+            }
+            Command::Check(_) => check(&source),
+            Command::Tokens(_) => dump_tokens(&source).map(|tokens| print!("{tokens}")),
+            Command::Fmt { check, .. } => return self.fmt(script, &source, *check, &reporter),
+            Command::Ast { format, .. } => {
+                dump_ast(&source, *format).map(|statements| print!("{statements}"))
+            }
+            Command::Repl => unreachable!("handled before reading anything"),
+        };
+        result.inspect_err(|e| reporter.report(e, &source))
+    }
+
+    // files are rewritten, anything else has nowhere to go back to so it's printed instead
+    fn fmt(
+        &self,
+        script: &Script,
+        source: &str,
+        check: bool,
+        reporter: &Reporter,
+    ) -> Result<(), Error> {
+        let formatted = formatter::format(source).inspect_err(|e| reporter.report(e, source))?;
+        let unchanged = match script {
+            // nobody types a newline at the end of -e code
+            Script::Code(_) => formatted.strip_suffix('\n') == Some(source),
+            Script::File(_) => formatted == source,
+        };
+        match script {
+            _ if check && !unchanged => Err(Error::Unformatted(
+                script.name().unwrap_or("<code>").to_string(),
+            )),
+            _ if check => Ok(()),
+            Script::File(path) if path != "-" => {
+                if unchanged {
+                    return Ok(());
+                }
+                fs::write(path, formatted)
+                    .map_err(|e| Error::IO(io::Error::new(e.kind(), format!("{path}: {e}"))))
+            }
+            _ => {
+                print!("{formatted}");
+                Ok(())
+            }
+        }
+    }
+
+    fn read(&self, script: &Script) -> Result<(String, Reporter), Error> {
+        let source = match script {
+            Script::File(path) => read_source(path)?,
+            Script::Code(code) => code.clone(),
+        };
+        Ok((source, Reporter::new(self.format, script.name())))
+    }
+}
+
+impl Script {
+    /// What errors call it, code from `-e` goes without
+    fn name(&self) -> Option<&str> {
+        match self {
+            Self::File(path) if path == "-" => Some("<stdin>"),
+            Self::File(path) => Some(path),
+            Self::Code(_) => None,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, Error> {
+        Cli::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    fn command(args: &str) -> Command {
+        parse(args).unwrap().command
+    }
+
+    #[test]
+    fn commands() {
+        let file = |path: &str| Script::File(path.to_string());
+        assert_eq!(command(""), Command::Repl);
+        assert_eq!(command("--vm repl"), Command::Repl);
+        assert_eq!(
+            command("a.lox"),
+            Command::Run {
+                script: file("a.lox"),
+                args: vec![]
+            }
+        );
+        assert_eq!(
+            command("run --vm a.lox --vm -x b"),
+            Command::Run {
+                script: file("a.lox"),
+                args: vec!["--vm".into(), "-x".into(), "b".into()]
+            }
+        );
+        assert_eq!(command("check -"), Command::Check(file("-")));
+        assert_eq!(
+            command("fmt --check a.lox"),
+            Command::Fmt {
+                script: file("a.lox"),
+                check: true
+            }
+        );
+        assert_eq!(command("tokens run"), Command::Tokens(file("run")));
+        assert_eq!(
+            command("ast -e print"),
+            Command::Ast {
+                script: Script::Code("print".into()),
+                format: AstFormat::Text
+            }
+        );
+        assert_eq!(
+            command("ast --json a.lox"),
+            Command::Ast {
+                script: file("a.lox"),
+                format: AstFormat::Json
+            }
+        );
+
+        // the --vm after the code is the script's, not ours
+        let cli = parse("--error-format=json -e 1 --vm").unwrap();
+        assert_eq!(cli.backend, Backend::TreeWalker);
+        assert_eq!(cli.format, ErrorFormat::Json);
+        assert_eq!(
+            cli.command,
+            Command::Run {
+                script: Script::Code("1".into()),
+                args: vec!["--vm".into()]
+            }
+        );
+
+        for args in [
+            "run",
+            "check",
+            "repl a.lox",
+            "check a.lox b.lox",
+            "-e",
+            "--nope",
+            "--error-format=xml a.lox",
+            "tokens -e 1 a.lox",
+            "check --check a.lox",
+            "--sexpr a.lox",
+        ] {
+            assert!(matches!(parse(args), Err(Error::Usage)), "{args:?}");
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use crate::error::Error;
+use crate::parser::ParsingError;
+use crate::resolver::ResolvingError;
+use crate::scanner::{ScanningError, Span};
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// An error ready to be shown to a person, pointing into the source it came from like
+///
+/// ```text
+/// parse error[E0203]: Expect ';' after expression.
+///  --> script.lox:1:8
+///   |
+/// 1 | print 1
+///   |        ^ expected ';' here
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// What stage the error came from, like "parse" or "runtime"
+    pub kind: &'static str,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub file: Option<String>,
+    pub span: Option<Span>,
+    /// Shown next to the underline, so only makes sense along with a span
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(kind: &'static str, message: impl ToString) -> Self {
+        Self {
+            kind,
+            code: None,
+            message: message.to_string(),
+            file: None,
+            span: None,
+            label: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_code(mut self, code: Option<&'static str>) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn with_file(mut self, file: Option<&str>) -> Self {
+        self.file = file.map(str::to_string);
+        self
+    }
+
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_label(mut self, label: Option<&str>) -> Self {
+        self.label = label.map(str::to_string);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl ToString) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// Prints to stderr, coloured if stdout is a terminal
+    pub fn emit(&self, source: &str) {
+        eprint!("{}", self.render(source, std::io::stdout().is_terminal()));
+    }
+
+    pub fn render(&self, source: &str, colour: bool) -> String {
+        let paint = |colour_code: &str, text: &str| {
+            if colour {
+                format!("{colour_code}{text}{RESET}")
+            } else {
+                text.to_string()
+            }
+        };
+        let mut out = String::new();
+        // writing to a String can't fail
+        let heading = match self.code {
+            Some(code) => format!("{} error[{code}]:", self.kind),
+            None => format!("{} error:", self.kind),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}",
+            paint(RED, &heading),
+            paint(BOLD, &format!(" {}", self.message))
+        );
+
+        let gutter = match self.span {
+            Some(span) => span.line.to_string().len(),
+            None => 0,
+        };
+        let blank = " ".repeat(gutter);
+        if let Some(span) = self.span {
+            let (text, underline) = snippet(source, span);
+            let bar = paint(BLUE, "|");
+            let location = match &self.file {
+                Some(file) => format!("{file}:{span}"),
+                None => span.to_string(),
+            };
+            let _ = writeln!(out, "{blank}{} {location}", paint(BLUE, "-->"));
+            let _ = writeln!(out, "{blank} {bar}");
+            let _ = writeln!(out, "{} {bar} {text}", paint(BLUE, &span.line.to_string()));
+            let underline = match &self.label {
+                Some(label) => format!("{underline} {label}"),
+                None => underline,
+            };
+            let _ = writeln!(out, "{blank} {bar} {}", paint(RED, &underline));
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "{blank} {} {note}", paint(BLUE, "= note:"));
+        }
+        out
+    }
+
+    /// A single line of JSON, missing fields are null
+    pub fn to_json(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+        let notes: Vec<_> = self.notes.iter().map(|note| json_string(note)).collect();
+        format!(
+            "{{\"kind\":{},\"code\":{},\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"notes\":[{}]}}",
+            json_string(self.kind),
+            optional(self.code.map(json_string)),
+            json_string(&self.message),
+            optional(self.file.as_deref().map(json_string)),
+            optional(self.span.map(|span| span.line.to_string())),
+            optional(self.span.map(|span| span.column.to_string())),
+            notes.join(","),
+        )
+    }
+}
+
+/// Quotes and escapes a string for JSON output
+pub fn json_string(string: &str) -> String {
+    let mut out = String::with_capacity(string.len() + 2);
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// the line the span starts on, and the padding and ^~~~ that go underneath it. Spans count bytes
+// but the terminal counts characters, and tabs are kept so the padding lines up with them
+fn snippet(source: &str, span: Span) -> (&str, String) {
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |newline| start + newline);
+    let text = source[line_start..line_end].trim_end_matches('\r');
+
+    let padding: String = source[line_start..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = source[start..span.end.clamp(start, line_end)]
+        .chars()
+        .count()
+        .max(1);
+    (text, format!("{padding}^{}", "~".repeat(width - 1)))
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::ScanningError(e) => Self::new("scan", e)
+                .with_span(e.span())
+                .with_label(scanning_label(e.kind())),
+            Error::ParsingError(e) => Self::new("parse", e)
+                .with_span(e.span())
+                .with_label(parsing_label(e.kind())),
+            Error::ResolvingError(e) => Self::new("resolve", e)
+                .with_span(e.span())
+                .with_label(resolving_label(e.kind())),
+            Error::CompileError(e) => Self::new("compile", e).with_span(e.span()),
+            Error::RuntimeError(e) => {
+                let mut diagnostic = Self::new("runtime", e).with_span(e.span());
+                // innermost first, so the function the error happened in is named too
+                for frame in e.trace() {
+                    diagnostic = diagnostic.with_note(frame);
+                }
+                diagnostic
+            }
+            Error::IO(e) => Self::new("io", e),
+            Error::Unformatted(_) => Self::new("fmt", error),
+            Error::Usage => Self::new("usage", error),
+            // callers wanting every error should go through Error::all
+            Error::Multiple(_) => Self::new("multiple", error),
+        }
+        .with_code(error.code())
+    }
+}
+
+fn scanning_label(error: &ScanningError) -> Option<&'static str> {
+    match error {
+        ScanningError::UntermString => Some("string starts here"),
+        ScanningError::Syntax => Some("unexpected character"),
+        _ => None,
+    }
+}
+
+fn parsing_label(error: &ParsingError) -> Option<&'static str> {
+    match error {
+        ParsingError::NoSemi | ParsingError::ConditionNoSemi => Some("expected ';' here"),
+        ParsingError::NoExpr => Some("expected an expression here"),
+        ParsingError::InvalidAssignment => Some("can't assign to the left of this"),
+        ParsingError::BreakOutsideLoop | ParsingError::ContinueOutsideLoop => {
+            Some("not inside a loop")
+        }
+        _ => None,
+    }
+}
+
+fn resolving_label(error: &ResolvingError) -> Option<&'static str> {
+    match error {
+        ResolvingError::OwnInitializer(_) => Some("read before it's defined"),
+        ResolvingError::AlreadyDeclared(_) => Some("declared again here"),
+        _ => None,
+    }
+}
+
+/// How errors get shown, picked with `--error-format`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Source snippets for people to read
+    #[default]
+    Human,
+    /// One JSON object per line for tools
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self, Error> {
+        match format {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => Err(Error::Usage),
+        }
+    }
+}
+
+/// Prints errors in the chosen format, naming the file they came from if there is one
+#[derive(Clone, Debug, Default)]
+pub struct Reporter {
+    pub format: ErrorFormat,
+    pub file: Option<String>,
+}
+
+impl Reporter {
+    pub fn new(format: ErrorFormat, file: Option<&str>) -> Self {
+        Self {
+            format,
+            file: file.map(str::to_string),
+        }
+    }
+
+    pub fn diagnostics(&self, error: &Error) -> Vec<Diagnostic> {
+        error
+            .all()
+            .into_iter()
+            .map(|error| Diagnostic::from(error).with_file(self.file.as_deref()))
+            .collect()
+    }
+
+    /// Renders every error against the source it came from and prints them to stderr
+    pub fn report(&self, error: &Error, source: &str) {
+        for diagnostic in self.diagnostics(error) {
+            match self.format {
+                ErrorFormat::Human => diagnostic.emit(source),
+                ErrorFormat::Json => eprintln!("{}", diagnostic.to_json()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interpreter;
+
+    fn rendered(source: &str) -> String {
+        let error = Interpreter::new().eval(source).unwrap_err();
+        Diagnostic::from(&error).render(source, false)
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        assert_eq!(
+            rendered("print 1"),
+            "parse error[E0203]: Expect ';' after expression.\n \
+             --> 1:8\n  \
+               |\n\
+             1 | print 1\n  \
+               |        ^ expected ';' here\n"
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        let source = "var s = \"tab\there\";\nprint @;\n\"oops";
+        let error = Interpreter::new().eval(source).unwrap_err();
+        let reporter = Reporter::new(ErrorFormat::Json, Some("dir\\a.lox"));
+        let lines: Vec<_> = reporter
+            .diagnostics(&error)
+            .iter()
+            .map(Diagnostic::to_json)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"kind":"scan","code":"E0102","message":"Unexpected Character","file":"dir\\a.lox","line":2,"column":7,"notes":[]}"#,
+                r#"{"kind":"scan","code":"E0101","message":"Unterminated String","file":"dir\\a.lox","line":3,"column":1,"notes":[]}"#,
+            ]
+        );
+        assert_eq!(json_string("\"\u{1}\n"), r#""\"\u0001\n""#);
+    }
+
+    #[test]
+    fn runtime_error_underline() {
+        let source = "fun f() {\n\treturn -\"héllo\";\n}\nf();";
+        assert_eq!(
+            rendered(source),
+            "runtime error[E0501]: Operator must be a number.\n \
+             --> 2:9\n  \
+               |\n\
+             2 | \treturn -\"héllo\";\n  \
+               | \t       ^~~~~~~~\n  \
+               = note: [line 2] in f()\n  \
+               = note: [line 4] in script\n"
+        );
+    }
+
+    #[test]
+    fn trace_names_every_function() {
+        let source = "fun a(n) { return b(n); }\nfun b(n) { return n + nil; }\nprint a(1);";
+        for error in [
+            Interpreter::new().eval(source).unwrap_err(),
+            crate::vm::Vm::new().eval(source).unwrap_err(),
+        ] {
+            assert_eq!(
+                Diagnostic::from(&error).notes,
+                ["[line 2] in b()", "[line 1] in a()", "[line 3] in script"]
+            );
+        }
+    }
+}
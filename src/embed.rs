@@ -0,0 +1,193 @@
+use crate::error::Error;
+use crate::interpreter::{self, Environment, RuntimeError};
+use crate::parser;
+use crate::resolver;
+use crate::syntax_trees::expression::Expression;
+use crate::syntax_trees::lox_callable::NativeFunction;
+use crate::syntax_trees::lox_object::LoxObject;
+use crate::syntax_trees::statement::Statement;
+use crate::token::SmartString;
+
+/// A Lox session that can be embedded in another program, globals stick around between calls
+/// to [`Interpreter::eval`] the same way they do in the REPL
+///
+/// Scripts that recurse anywhere near the call depth limit need a thread with
+/// [`TREE_WALKER_STACK`](crate::TREE_WALKER_STACK) of stack to run on
+pub struct Interpreter {
+    env: Environment,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut env = Environment::default();
+        interpreter::natives::define(&mut env);
+        Self { env }
+    }
+
+    /// Runs the source, returning the value of the last statement if it's an expression and
+    /// nil otherwise
+    pub fn eval(&mut self, source: &str) -> Result<LoxObject, Error> {
+        let mut statements = parser::parse(source)?;
+        resolver::resolve(&mut statements)?;
+
+        let tail = match statements.pop() {
+            Some(Statement::Expression { expression, .. }) => Some(expression),
+            Some(statement) => {
+                statements.push(statement);
+                None
+            }
+            None => None,
+        };
+
+        interpreter::interpret(statements, &mut self.env)?;
+        match tail {
+            Some(expression) => Ok(interpreter::evaluate(expression, &mut self.env)?),
+            None => Ok(LoxObject::Nil),
+        }
+    }
+
+    /// Runs an expression that's already been parsed, like the REPL does with bare expressions
+    pub fn evaluate(&mut self, mut expression: Expression) -> Result<LoxObject, Error> {
+        resolver::resolve_expression(&mut expression)?;
+        Ok(interpreter::evaluate(expression, &mut self.env)?)
+    }
+
+    pub fn define_global(&mut self, name: &str, value: LoxObject) {
+        self.env.define_global(name, Some(value));
+    }
+
+    /// Every global sorted by name, declared but uninitialized globals are nil
+    pub fn globals(&self) -> Vec<(SmartString, LoxObject)> {
+        let mut globals: Vec<_> = self
+            .env
+            .globals()
+            .into_iter()
+            .map(|(name, value)| (name, value.unwrap_or(LoxObject::Nil)))
+            .collect();
+        globals.sort_by(|(a, _), (b, _)| a.cmp(b));
+        globals
+    }
+
+    /// Returns None if the global was never declared, declared but uninitialized globals are nil
+    pub fn get_global(&self, name: &str) -> Option<LoxObject> {
+        match self.env.get_global(name) {
+            Ok(value) => Some(value.clone().unwrap_or(LoxObject::Nil)),
+            Err(_) => None,
+        }
+    }
+
+    /// Exposes a Rust closure to scripts as a global function, the interpreter checks arity
+    /// before calling it
+    pub fn register_fn<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(Vec<LoxObject>) -> Result<LoxObject, RuntimeError> + 'static,
+    {
+        let native = NativeFunction::new(name, arity, function);
+        self.define_global(name, LoxObject::Native(native));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn eval_returns_last_expression() {
+        let mut lox = Interpreter::new();
+        assert_eq!(lox.eval("1 + 2;").unwrap(), LoxObject::Float(3.0));
+        assert_eq!(lox.eval("var a = 1;").unwrap(), LoxObject::Nil);
+    }
+
+    #[test]
+    fn state_persists() {
+        let mut lox = Interpreter::new();
+        lox.eval("var a = 1;").unwrap();
+        lox.eval("a = a + 1;").unwrap();
+        assert_eq!(lox.get_global("a"), Some(LoxObject::Float(2.0)));
+        assert_eq!(lox.get_global("b"), None);
+    }
+
+    #[test]
+    fn closures_share_captured_variables() {
+        let mut lox = Interpreter::new();
+        lox.eval(
+            "fun pair() { var v = 0; fun inc() { v = v + 1; } fun get() { return v; } inc(); inc(); return get; }",
+        )
+        .unwrap();
+        assert_eq!(lox.eval("pair()();").unwrap(), LoxObject::Float(2.0));
+    }
+
+    #[test]
+    fn host_globals() {
+        let mut lox = Interpreter::new();
+        lox.define_global("name", LoxObject::String("lox".into()));
+        assert_eq!(
+            lox.eval("\"hi \" + name;").unwrap(),
+            LoxObject::String("hi lox".into())
+        );
+    }
+
+    #[test]
+    fn register_fn() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut lox = Interpreter::new();
+        let log = seen.clone();
+        lox.register_fn("log", 1, move |args| {
+            log.borrow_mut().extend(args);
+            Ok(LoxObject::Nil)
+        });
+        lox.eval("log(1); log(\"two\");").unwrap();
+        assert_eq!(
+            *seen.borrow(),
+            vec![LoxObject::Float(1.0), LoxObject::String("two".into())]
+        );
+        assert!(lox.eval("log();").is_err());
+    }
+
+    #[test]
+    fn lists() {
+        let mut lox = Interpreter::new();
+        lox.eval("var xs = [1, 2]; var ys = xs; push(ys, 3); xs[0] = remove(xs, 2);")
+            .unwrap();
+        assert_eq!(lox.eval("len(xs);").unwrap(), LoxObject::Float(2.0));
+        assert_eq!(lox.eval("xs[0] + xs[1];").unwrap(), LoxObject::Float(5.0));
+        let Err(Error::RuntimeError(RuntimeError::Traceback { error, .. })) = lox.eval("xs[2];")
+        else {
+            panic!("expected a runtime error");
+        };
+        assert!(matches!(
+            *error,
+            RuntimeError::IndexOutOfRange { len: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn stack_trace() {
+        let mut lox = Interpreter::new();
+        let source = "fun inner() {\n  return -nil;\n}\nfun outer() {\n  inner();\n}\nouter();";
+        let Err(Error::RuntimeError(e)) = lox.eval(source) else {
+            panic!("expected a runtime error");
+        };
+        let trace: Vec<_> = e.trace().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            trace,
+            [
+                "[line 2] in inner()",
+                "[line 5] in outer()",
+                "[line 7] in script"
+            ]
+        );
+        // the innermost frame points at the exact expression that failed
+        let span = e.span().unwrap();
+        assert_eq!(&source[span.start..span.end], "-nil");
+        assert_eq!(span.column, 10);
+    }
+}
@@ -1,35 +1,121 @@
 use crate::interpreter::RuntimeError;
 use crate::parser::ParsingError;
+use crate::resolver::ResolvingError;
 pub use crate::scanner::ScanningError;
+use crate::vm::CompileError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("Usage: jlox [script] | jlox")]
+    #[error(
+        "Usage: lox [--vm] [--error-format=human|json] [command] [--check | --json | --sexpr] [script | - | -e <code>] [args...]
+
+Commands:
+    run     run the script, the default when there is one
+    repl    start an interactive session, the default when there isn't
+    check   scan, parse and resolve the script without running it
+    tokens  print the tokens the scanner makes of the script
+    ast     print the statements the parser makes of the script, as JSON with --json or
+            S-expressions with --sexpr
+    fmt     rewrite the script in the standard style, or with --check fail if that would
+            change it"
+    )]
     Usage,
+    /// From `lox fmt --check`, naming the file that would change
+    #[error("{0} isn't formatted")]
+    Unformatted(String),
     #[error("IO Error: {0}")]
     IO(#[from] std::io::Error),
 
-    #[error("Sorry, I only except Ascii (my bad, its a skill issue)")]
-    NotAscii,
-
     #[error("Scanning Error: {0}")]
     ScanningError(#[from] ScanningError),
 
     #[error("Parsing Error: {0}")]
     ParsingError(#[from] ParsingError),
 
+    #[error("Resolving Error: {0}")]
+    ResolvingError(#[from] ResolvingError),
+
+    #[error("Compile Error: {0}")]
+    CompileError(#[from] CompileError),
+
     #[error("Runtime Error: {0}")]
     RuntimeError(#[from] RuntimeError),
+
+    /// Scanning, parsing and resolving carry on after an error so they can report everything
+    /// that's wrong at once
+    #[error("{} errors", .0.len())]
+    Multiple(Vec<Error>),
+}
+
+impl<E: Into<Error>> From<Vec<E>> for Error {
+    fn from(errors: Vec<E>) -> Self {
+        let mut errors: Vec<Error> = errors.into_iter().map(Into::into).collect();
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            Self::Multiple(errors)
+        }
+    }
 }
 
 impl Error {
-    pub fn error(self, line: u32) -> Self {
-        self.report(line, "");
-        self
+    /// A stable identifier for tools to match on. Codes are grouped by stage, new variants get
+    /// new codes and old ones are never reused
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::Usage => Some("E0001"),
+            Self::IO(_) => Some("E0002"),
+            Self::Unformatted(_) => Some("E0003"),
+            Self::ScanningError(e) => Some(e.code()),
+            Self::ParsingError(e) => Some(e.code()),
+            Self::ResolvingError(e) => Some(e.code()),
+            Self::CompileError(e) => Some(e.code()),
+            Self::RuntimeError(e) => Some(e.code()),
+            // only ever holds other errors, which have codes of their own
+            Self::Multiple(_) => None,
+        }
+    }
+
+    /// The status the binary exits with, following the sysexits.h codes jlox and clox use
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            // like diff, 1 just means the file and its formatting differ
+            Self::Unformatted(_) => 1,
+            Self::Usage => 64,
+            Self::ScanningError(_)
+            | Self::ParsingError(_)
+            | Self::ResolvingError(_)
+            | Self::CompileError(_) => 65,
+            Self::RuntimeError(_) => 70,
+            Self::IO(_) => 74,
+            // static errors are all collected together, so they share a code anyway
+            Self::Multiple(errors) => errors.first().map_or(65, Error::exit_code),
+        }
+    }
+
+    /// Every error, with Multiple flattened out
+    pub fn all(&self) -> Vec<&Error> {
+        match self {
+            Self::Multiple(errors) => errors.iter().flat_map(Error::all).collect(),
+            error => vec![error],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Interpreter;
+
+    fn exit_code(source: &str) -> u8 {
+        Interpreter::new().eval(source).unwrap_err().exit_code()
     }
 
-    pub fn report(&self, line: u32, whre: &str) {
-        eprintln!("[line {}] at {}: {}", line, whre, self);
+    #[test]
+    fn exit_codes() {
+        assert_eq!(exit_code("print 1"), 65);
+        assert_eq!(exit_code("print @; print #;"), 65);
+        assert_eq!(exit_code("return;"), 65);
+        assert_eq!(exit_code("print -nil;"), 70);
     }
 }
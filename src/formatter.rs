@@ -0,0 +1,484 @@
+use crate::error::Error;
+use crate::parser;
+use crate::scanner::{Comment, Span};
+use crate::syntax_trees::expression::Expression;
+use crate::syntax_trees::lox_object::LoxObject;
+use crate::syntax_trees::statement::{Function, Statement};
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+const INDENT: &str = "    ";
+
+/// Parses the source and prints it back out the one canonical way. Comments are kept, and so
+/// are blank lines between statements, though never more than one in a row
+pub fn format(source: &str) -> Result<String, Error> {
+    let (statements, comments) = parser::parse_with_comments(source)?;
+    let mut formatter = Formatter {
+        source,
+        comments: comments.into_iter().peekable(),
+        out: String::new(),
+        depth: 0,
+        last_line: None,
+        closing: source.len(),
+    };
+    formatter.items(
+        &statements,
+        source.len(),
+        Statement::span,
+        Formatter::statement,
+    );
+    Ok(formatter.out)
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    // the ones still to be written, in the order they appear
+    comments: Peekable<IntoIter<Comment>>,
+    out: String,
+    depth: usize,
+    // the source line the last thing written ended on, None at the start of a block
+    last_line: Option<u32>,
+    // where the block being written closes, comments past it belong to whatever holds the block
+    closing: usize,
+}
+
+impl Formatter<'_> {
+    /// Writes each item on its own lines, along with the comments around it. Comments that
+    /// can't be put back exactly where they were, like ones inside an expression, come out
+    /// before the next thing after them
+    fn items<T>(
+        &mut self,
+        items: &[T],
+        end: usize,
+        span: fn(&T) -> Span,
+        write: fn(&mut Self, &T),
+    ) {
+        for item in items {
+            let span = span(item);
+            self.comments_before(span.start);
+            self.blank_line(span.line);
+            self.indent();
+            write(self, item);
+            let end_line = self.end_line(span);
+            self.trailing_comment(span.end, end_line);
+            self.out.push('\n');
+            self.last_line = Some(end_line);
+        }
+        self.comments_before(end);
+    }
+
+    /// The items between braces, with the closing brace at `end`
+    fn braces<T>(
+        &mut self,
+        items: &[T],
+        end: usize,
+        span: fn(&T) -> Span,
+        write: fn(&mut Self, &T),
+    ) {
+        self.out.push('{');
+        if items.is_empty() && self.comments.peek().is_none_or(|c| c.span.start >= end) {
+            self.out.push('}');
+            return;
+        }
+        self.out.push('\n');
+        self.depth += 1;
+        self.last_line = None;
+        let closing = std::mem::replace(&mut self.closing, end);
+        self.items(items, end, span, write);
+        self.closing = closing;
+        self.depth -= 1;
+        self.indent();
+        self.out.push('}');
+    }
+
+    fn comments_before(&mut self, position: usize) {
+        while let Some(comment) = self.comments.next_if(|c| c.span.start < position) {
+            if self.out.is_empty() || self.out.ends_with('\n') {
+                self.blank_line(comment.span.line);
+                self.indent();
+                self.out.push_str(&comment.text);
+                self.out.push('\n');
+            } else {
+                // partway through a line, so whatever comes next has to move down one
+                self.out.push(' ');
+                self.out.push_str(&comment.text);
+                self.out.push('\n');
+                self.indent();
+            }
+            // a comment that got moved down mustn't pull the line count back
+            self.last_line = self.last_line.max(Some(comment.span.line));
+        }
+    }
+
+    // only called right before a newline gets written, or the comment would swallow code
+    fn trailing_comment(&mut self, end: usize, line: u32) {
+        if let Some(comment) = self
+            .comments
+            .next_if(|c| c.span.start >= end && c.span.start < self.closing && c.span.line == line)
+        {
+            self.out.push(' ');
+            self.out.push_str(&comment.text);
+        }
+    }
+
+    fn blank_line(&mut self, line: u32) {
+        if self.last_line.is_some_and(|last| line > last + 1) {
+            self.out.push('\n');
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn end_line(&self, span: Span) -> u32 {
+        span.line + self.source[span.start..span.end].matches('\n').count() as u32
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        if let Some((initializer, looped)) = self.desugared_for(statement) {
+            return self.for_loop(initializer, looped);
+        }
+        match statement {
+            Statement::Expression { expression: e, .. } => {
+                self.out.push_str(&format!("{};", expression(e)))
+            }
+            Statement::Print { expression: e, .. } => {
+                self.out.push_str(&format!("print {};", expression(e)))
+            }
+            Statement::Var {
+                name,
+                initializer: Some(initializer),
+                ..
+            } => self
+                .out
+                .push_str(&format!("var {name} = {};", expression(initializer))),
+            Statement::Var { name, .. } => self.out.push_str(&format!("var {name};")),
+            Statement::While {
+                condition,
+                body,
+                span,
+                ..
+            } => {
+                self.out
+                    .push_str(&format!("while ({})", expression(condition)));
+                self.body(body, span.end);
+            }
+            Statement::Function(function) => {
+                self.out.push_str("fun ");
+                self.function(function);
+            }
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+                span,
+            } => {
+                self.out.push_str(&format!("class {name} "));
+                if let Some(superclass) = superclass {
+                    self.out.push_str(&format!("< {} ", expression(superclass)));
+                }
+                self.braces(methods, span.end, |method| method.span, Self::function);
+            }
+            Statement::If {
+                condition,
+                then,
+                else_case,
+                span,
+            } => {
+                self.out
+                    .push_str(&format!("if ({})", expression(condition)));
+                self.body(then, span.end);
+                let Some(else_case) = else_case else {
+                    return;
+                };
+                if self.braced(then) {
+                    self.out.push_str(" else");
+                } else {
+                    self.out.push('\n');
+                    self.indent();
+                    self.out.push_str("else");
+                }
+                if matches!(**else_case, Statement::If { .. }) {
+                    self.out.push(' ');
+                    self.statement(else_case);
+                } else {
+                    self.body(else_case, span.end);
+                }
+            }
+            Statement::Block { statements, span } => {
+                self.braces(statements, span.end, Statement::span, Self::statement)
+            }
+            Statement::Return {
+                value: Some(value), ..
+            } => self.out.push_str(&format!("return {};", expression(value))),
+            Statement::Return { value: None, .. } => self.out.push_str("return;"),
+            Statement::Break { .. } => self.out.push_str("break;"),
+            Statement::Continue { .. } => self.out.push_str("continue;"),
+        }
+    }
+
+    /// Blocks go on the same line as the statement they belong to, anything else gets a line
+    /// of its own. `end` is where that statement ends
+    fn body(&mut self, body: &Statement, end: usize) {
+        if self.braced(body) {
+            self.out.push(' ');
+            self.statement(body);
+            return;
+        }
+        let span = body.span();
+        self.out.push('\n');
+        self.depth += 1;
+        self.last_line = None;
+        self.comments_before(span.start);
+        self.indent();
+        self.statement(body);
+        // a then with an else after it can only have a comment from before the else, one
+        // after that is the whole if's
+        let closing = match span.end {
+            last if last == end => self.closing,
+            _ => end,
+        };
+        let closing = std::mem::replace(&mut self.closing, closing);
+        self.trailing_comment(span.end, self.end_line(span));
+        self.closing = closing;
+        self.depth -= 1;
+    }
+
+    fn braced(&self, statement: &Statement) -> bool {
+        matches!(statement, Statement::Block { .. }) && self.desugared_for(statement).is_none()
+    }
+
+    fn function(&mut self, function: &Function) {
+        let params: Vec<_> = function.params.iter().map(ToString::to_string).collect();
+        self.out
+            .push_str(&format!("{}({}) ", function.name, params.join(", ")));
+        self.braces(
+            &function.body,
+            function.span.end,
+            Statement::span,
+            Self::statement,
+        );
+    }
+
+    // the parser turns for loops into a while, wrapped in a block sharing its span when there's
+    // an initializer
+    fn desugared_for<'s>(
+        &self,
+        statement: &'s Statement,
+    ) -> Option<(Option<&'s Statement>, &'s Statement)> {
+        let is_for =
+            |statement: &Statement| matches!(statement, Statement::While { for_loop: true, .. });
+        match statement {
+            Statement::While { .. } if is_for(statement) => Some((None, statement)),
+            Statement::Block { statements, span } => match statements.as_slice() {
+                [initializer, looped] if is_for(looped) && looped.span().start == span.start => {
+                    Some((Some(initializer), looped))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn for_loop(&mut self, initializer: Option<&Statement>, looped: &Statement) {
+        let Statement::While {
+            condition,
+            body,
+            increment,
+            span,
+            ..
+        } = looped
+        else {
+            unreachable!("checked by desugared_for");
+        };
+        self.out.push_str("for (");
+        match initializer {
+            Some(initializer) => self.statement(initializer),
+            None => self.out.push(';'),
+        }
+        // a missing condition becomes a true that points at the for keyword
+        let omitted = matches!(
+            condition,
+            Expression::Literal { value: LoxObject::Bool(true), span: literal }
+                if literal.start == span.start
+        );
+        if !omitted {
+            self.out.push_str(&format!(" {}", expression(condition)));
+        }
+        self.out.push(';');
+        if let Some(increment) = increment {
+            self.out.push_str(&format!(" {}", expression(increment)));
+        }
+        self.out.push(')');
+        self.body(body, span.end);
+    }
+}
+
+// groupings are kept in the tree, so the parentheses that were written are exactly the ones
+// needed and none have to be added
+fn expression(e: &Expression) -> String {
+    let list = |elements: &[Expression]| {
+        let elements: Vec<_> = elements.iter().map(expression).collect();
+        elements.join(", ")
+    };
+    match e {
+        Expression::Binary {
+            left,
+            operator,
+            right,
+            ..
+        } => format!("{} {operator} {}", expression(left), expression(right)),
+        Expression::Logical {
+            left,
+            operator,
+            right,
+            ..
+        } => format!("{} {operator} {}", expression(left), expression(right)),
+        Expression::Grouping { inner, .. } => format!("({})", expression(inner)),
+        Expression::Literal {
+            value: LoxObject::String(string),
+            ..
+        } => format!("\"{string}\""),
+        Expression::Literal { value, .. } => value.to_string(),
+        Expression::Call { callee, args, .. } => {
+            format!("{}({})", expression(callee), list(args))
+        }
+        Expression::Unary {
+            operator, inner, ..
+        } => format!("{operator}{}", expression(inner)),
+        Expression::Variable { name, .. } => name.to_string(),
+        Expression::Assign { name, value, .. } => {
+            format!("{name} = {}", expression(value))
+        }
+        Expression::Get { object, name, .. } => format!("{}.{name}", expression(object)),
+        Expression::Set {
+            object,
+            name,
+            value,
+            ..
+        } => format!("{}.{name} = {}", expression(object), expression(value)),
+        Expression::This { .. } => "this".to_string(),
+        Expression::Super { method, .. } => format!("super.{method}"),
+        Expression::List { elements, .. } => format!("[{}]", list(elements)),
+        Expression::Map { entries, .. } => {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(key, value)| format!("{}: {}", expression(key), expression(value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Expression::Index { object, index, .. } => {
+            format!("{}[{}]", expression(object), expression(index))
+        }
+        Expression::SetIndex {
+            object,
+            index,
+            value,
+            ..
+        } => format!(
+            "{}[{}] = {}",
+            expression(object),
+            expression(index),
+            expression(value)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // formatting again shouldn't change anything, and the tree has to survive the trip
+    fn round_trip(source: &str) -> String {
+        let formatted = format(source).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted, "not idempotent");
+        let tree = |source: &str| {
+            parser::parse(source)
+                .unwrap()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tree(&formatted), tree(source));
+        formatted
+    }
+
+    #[test]
+    fn layout() {
+        let source = "// fib
+fun fib(n){if(n<2)return n;else{return fib(n-1)+fib(n - 2);}}
+
+
+class B<A{init(x){this.x=x;// keep
+}get(){return super.get()*(1+2);}}
+for(var i=0;i<3;i=i+1)print[i,{\"k\":-i},!true and nil];
+for(;;){break;}
+var m;m[0]=x.y=1;
+while(false){}
+if (a) {} else if (b) print \"multi
+line\"; // trailing
+// the end
+";
+        assert_eq!(
+            round_trip(source),
+            "// fib
+fun fib(n) {
+    if (n < 2)
+        return n;
+    else {
+        return fib(n - 1) + fib(n - 2);
+    }
+}
+
+class B < A {
+    init(x) {
+        this.x = x; // keep
+    }
+    get() {
+        return super.get() * (1 + 2);
+    }
+}
+for (var i = 0; i < 3; i = i + 1)
+    print [i, {\"k\": -i}, !true and nil];
+for (;;) {
+    break;
+}
+var m;
+m[0] = x.y = 1;
+while (false) {}
+if (a) {} else if (b)
+    print \"multi
+line\"; // trailing
+// the end
+"
+        );
+    }
+
+    #[test]
+    fn stray_comments() {
+        let formatted =
+            round_trip("var a = [1, // one\n 2];\n{\n  // only\n}\nif (x) // why\n  y();\n");
+        assert_eq!(
+            formatted,
+            "var a = [1, 2];\n// one\n{\n    // only\n}\nif (x)\n    // why\n    y();\n"
+        );
+        // the comment is after the else, not the then
+        let formatted = round_trip("if (a) print 1; else print 2; // c1\n");
+        assert_eq!(
+            formatted,
+            "if (a)\n    print 1;\nelse\n    print 2; // c1\n"
+        );
+    }
+
+    #[test]
+    fn comment_after_closing_brace() {
+        let formatted = round_trip("class A {\n    m() { return 1; } // after m\n}\n");
+        assert_eq!(
+            formatted,
+            "class A {\n    m() {\n        return 1;\n    } // after m\n}\n"
+        );
+    }
+}
@@ -2,307 +2,147 @@ use crate::interpreter::error::RuntimeError;
 use crate::interpreter::Result;
 use crate::syntax_trees::lox_object::LoxObject;
 use crate::token::SmartString;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt;
-use std::ptr::NonNull;
-
-use std::collections::hash_map::Entry;
+use std::rc::Rc;
 
 type Env = HashMap<SmartString, Option<LoxObject>>;
 
-pub struct EnvironmentTree {
-    global: GlobalEnv,
-    leaves: Vec<Leaf>,
-}
-
-#[derive(Clone)]
-enum Leaf {
-    Node(Node),
-    Closure(Closure),
-}
-
-impl Environment for Leaf {
-    fn get(&self, key: &str) -> Result<Option<LoxObject>> {
-        match self {
-            Self::Node(node) => node.get(key),
-            Self::Closure(closure) => closure.get(key),
-        }
-    }
-
-    fn define(&mut self, key: SmartString, value: Option<LoxObject>) {
-        match self {
-            Self::Node(node) => node.define(key, value),
-            Self::Closure(closure) => closure.define(key, value),
-        }
-    }
-
-    fn assign(&mut self, key: SmartString, value: LoxObject) -> Result<()> {
-        match self {
-            Self::Node(node) => node.assign(key, value),
-            Self::Closure(closure) => closure.assign(key, value),
-        }
-    }
-}
+// frames are shared between every environment (and every closure) that can see them, so writes
+// through one are visible through all of them. Closures stored in the frame they capture form a
+// cycle, which we accept rather than pulling in a garbage collector
+type Scope = Rc<RefCell<Frame>>;
 
-impl Default for EnvironmentTree {
-    fn default() -> Self {
-        Self::new()
-    }
+#[derive(Default)]
+struct Frame {
+    values: Env,
+    parent: Option<Scope>,
 }
 
-#[derive(Debug)]
-struct GlobalEnv(pub Env);
-
-impl std::ops::Deref for GlobalEnv {
-    type Target = Env;
-    fn deref(&self) -> &Env {
-        &self.0
+impl fmt::Debug for Frame {
+    // only list the names, values can be closures that point back at this frame
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.values.keys()).finish()
     }
 }
 
-impl std::ops::DerefMut for GlobalEnv {
-    fn deref_mut(&mut self) -> &mut Env {
-        &mut self.0
-    }
+/// A handle to the innermost scope, cloning it is cheap and the clone shares every frame
+#[derive(Clone, Debug)]
+pub struct Environment {
+    current: Scope,
+    global: Scope,
+    // how many Lox calls are running, shared the same way the global frame is
+    calls: Rc<Cell<usize>>,
 }
 
-impl EnvironmentTree {
-    pub fn add_scope(&mut self) {
-        // using cur_node here causes borrowck issues
-        match self.leaves.last_mut() {
-            Some(cur) => *cur = Leaf::Node(Node::new_scoped(cur.clone())),
-            None => {
-                self.leaves
-                    .push(Leaf::Node(Node::new_global(&mut self.global)));
-            }
-        }
-    }
-
-    pub fn add_function_scope(&mut self, closure: Closure) {
-        self.leaves.push(Leaf::Closure(closure));
-    }
-
-    fn cur_node(&mut self) -> Option<&mut Leaf> {
-        self.leaves.last_mut()
-    }
-
-    fn cur_borrow(&self) -> Option<&Leaf> {
-        self.leaves.last()
-    }
-
-    pub fn remove_scope(&mut self) {
-        let leaf = self.cur_node().expect("tried to remove Global Scope");
-
-        match leaf {
-            Leaf::Node(node) => {
-                if let Parent::Scoped(parent) = &mut node.parent {
-                    *node = *parent.clone();
-                } else {
-                    self.leaves.pop();
-                }
-            }
-
-            Leaf::Closure(closure) => {
-                if let Some(parent) = &mut closure.parent {
-                    *closure = *parent.clone()
-                } else {
-                    self.leaves.pop();
-                }
-            }
-        }
-    }
-
-    pub fn cur_leaf(&self) -> &dyn Environment {
-        let Some(last) = self.leaves.last() else {
-            return &self.global;
-        };
-        last
-    }
-
-    pub fn cur_mut(&mut self) -> &mut dyn Environment {
-        let Some(last) = self.leaves.last_mut() else {
-            return &mut self.global;
-        };
-        last
-    }
-
-    fn new() -> Self {
+impl Default for Environment {
+    fn default() -> Self {
+        let global = Scope::default();
         Self {
-            global: GlobalEnv(Env::new()),
-            leaves: Vec::new(),
-        }
-    }
-
-    pub fn new_closure(&self) -> Closure {
-        if let Some(last) = self.leaves.last() {
-            Closure::from(last)
-        } else {
-            Closure::from(&self.global)
+            current: global.clone(),
+            global,
+            calls: Rc::default(),
         }
     }
 }
 
-#[derive(Clone, Debug)]
-struct Node {
-    inner: Env,
-    parent: Parent,
-}
-
-#[derive(Clone, Debug)]
-enum Parent {
-    Global(NonNull<GlobalEnv>),
-    Scoped(Box<Node>),
-}
-
-#[derive(Clone, Debug)]
-pub struct Closure {
-    env: Env,
-    parent: Option<Box<Closure>>,
-}
-
-impl From<&Leaf> for Closure {
-    fn from(leaf: &Leaf) -> Self {
-        match leaf {
-            Leaf::Closure(closure) => closure.clone(),
-            Leaf::Node(node) => node.into(),
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.global.borrow())?;
+        let mut scope = Some(self.current.clone());
+        while let Some(frame) = scope.filter(|frame| !Rc::ptr_eq(frame, &self.global)) {
+            write!(f, "|{:?}", frame.borrow())?;
+            scope = frame.borrow().parent.clone();
         }
+        Ok(())
     }
 }
 
-impl From<&GlobalEnv> for Closure {
-    fn from(env: &GlobalEnv) -> Self {
-        Self {
-            env: env.0.clone(),
-            parent: None,
+impl Environment {
+    pub fn get_global(&self, key: &str) -> Result<Option<LoxObject>> {
+        match self.global.borrow().values.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => Err(RuntimeError::Undefined(key.into())),
         }
     }
-}
 
-impl From<&Node> for Closure {
-    fn from(node: &Node) -> Self {
-        let parent: Closure = match node.parent {
-            Parent::Global(global) => unsafe { global.as_ref() }.into(),
-            Parent::Scoped(ref node) => node.as_ref().into(),
-        };
-        let parent = Some(Box::new(parent));
-        Self {
-            env: node.inner.clone(),
-            parent,
+    /// Looks the key up exactly depth scopes up, as worked out by the resolver
+    pub fn get_at(&self, depth: usize, key: &str) -> Result<Option<LoxObject>> {
+        match self.ancestor(depth).borrow().values.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => Err(RuntimeError::Undefined(key.into())),
         }
     }
-}
 
-pub trait Environment {
-    fn get(&self, key: &str) -> Result<Option<LoxObject>>;
-    fn define(&mut self, key: SmartString, value: Option<LoxObject>);
-    fn assign(&mut self, key: SmartString, value: LoxObject) -> Result<()>;
-}
-
-impl Environment for Node {
-    fn get(&self, key: &str) -> Result<Option<LoxObject>> {
-        if let Some(value) = self.inner.get(key) {
-            Ok(value.clone())
-        } else {
-            match &self.parent {
-                Parent::Scoped(outer) => outer.get(key),
-                // SAFETY: valid because new Envs are only created through Nodes or EnvironmenTree, which both create valid types
-                // can't be mutated because we'll be accessing this through EnvironmentTree, which
-                // can only mutate Global if it's exclusive, there are no other threads beacuse
-                // we're not Sync,
-                // edge case to consider:
-                // assign("val", val) if val is in global scope
-                // it's okay because we return a copy, ensuring that the reference doesn't exist
-                Parent::Global(environment) => unsafe { environment.as_ref() }.get(key),
+    pub fn assign_at(&mut self, depth: usize, key: &str, value: LoxObject) -> Result<()> {
+        match self.ancestor(depth).borrow_mut().values.get_mut(key) {
+            Some(variable) => {
+                *variable = Some(value);
+                Ok(())
             }
+            None => Err(RuntimeError::Undefined(key.into())),
         }
     }
 
-    fn define(&mut self, key: SmartString, value: Option<LoxObject>) {
-        self.inner.insert(key, value);
-    }
-
-    fn assign(&mut self, key: SmartString, value: LoxObject) -> Result<()> {
-        if let Entry::Occupied(mut variable) = self.inner.entry(key.clone()) {
-            *variable.get_mut() = Some(value);
-            Ok(())
-        } else {
-            match &mut self.parent {
-                // SAFETY: see get() for validity/alignment safety
-                // Other references can only be created through this function or through EnvironmentTree,
-                // which is borrowed mutably for assign_global to be called, so we're safe
-                Parent::Global(g) => unsafe { g.as_mut() }.assign(key, value),
-                Parent::Scoped(outer) => outer.assign(key, value),
+    pub fn assign_global(&mut self, key: &str, value: LoxObject) -> Result<()> {
+        match self.global.borrow_mut().values.get_mut(key) {
+            Some(variable) => {
+                *variable = Some(value);
+                Ok(())
             }
+            None => Err(RuntimeError::Undefined(key.into())),
         }
     }
-}
 
-impl Environment for Closure {
-    fn get(&self, key: &str) -> Result<Option<LoxObject>> {
-        if let Some(value) = self.env.get(key) {
-            Ok(value.clone())
-        } else {
-            match &self.parent {
-                Some(closure) => closure.get(key),
-                None => Err(RuntimeError::Undefined(key.into())),
-            }
+    fn ancestor(&self, depth: usize) -> Scope {
+        let mut scope = self.current.clone();
+        for _ in 0..depth {
+            let parent = scope.borrow().parent.clone();
+            scope = parent.expect("resolver depth is deeper than the scope chain");
         }
+        scope
     }
 
-    fn define(&mut self, key: SmartString, value: Option<LoxObject>) {
-        self.env.insert(key, value);
-    }
-
-    fn assign(&mut self, key: SmartString, value: LoxObject) -> Result<()> {
-        if let Entry::Occupied(mut variable) = self.env.entry(key.clone()) {
-            *variable.get_mut() = Some(value);
-            Ok(())
-        } else {
-            match &mut self.parent {
-                Some(closure) => closure.assign(key, value),
-                None => Err(RuntimeError::Undefined(key.into())),
-            }
-        }
+    /// Every global and its value, in no particular order
+    pub fn globals(&self) -> Vec<(SmartString, Option<LoxObject>)> {
+        let global = self.global.borrow();
+        global
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
     }
-}
 
-impl Environment for GlobalEnv {
-    fn get(&self, key: &str) -> Result<Option<LoxObject>> {
-        if let Some(value) = self.0.get(key) {
-            Ok(value.clone())
-        } else {
-            Err(RuntimeError::Undefined(SmartString::from(key)))
-        }
+    pub fn define_global(&mut self, key: &str, value: Option<LoxObject>) {
+        self.global.borrow_mut().values.insert(key.into(), value);
     }
 
-    fn define(&mut self, key: SmartString, value: Option<LoxObject>) {
-        self.0.insert(key, value);
+    pub fn define(&mut self, key: &str, value: Option<LoxObject>) {
+        self.current.borrow_mut().values.insert(key.into(), value);
     }
 
-    fn assign(&mut self, key: SmartString, value: LoxObject) -> Result<()> {
-        match self.0.entry(key.clone()) {
-            Entry::Occupied(mut variable) => {
-                *variable.get_mut() = Some(value);
-                Ok(())
-            }
-            Entry::Vacant(_) => Err(RuntimeError::Undefined(key)),
-        }
+    /// The number of calls running in this session, everything sharing its globals counts
+    pub(crate) fn calls(&self) -> &Rc<Cell<usize>> {
+        &self.calls
     }
-}
 
-impl Node {
-    fn new_global(env: &mut GlobalEnv) -> Self {
-        Self {
-            inner: Env::new(),
-            parent: Parent::Global(env.into()),
-        }
+    pub fn add_scope(&mut self) {
+        let frame = Frame {
+            values: Env::new(),
+            parent: Some(self.current.clone()),
+        };
+        self.current = Rc::new(RefCell::new(frame));
     }
 
-    fn new_scoped(parent: Node) -> Self {
-        Self {
-            inner: Env::new(),
-            parent: Parent::Scoped(Box::new(parent)),
-        }
+    pub fn remove_scope(&mut self) {
+        let parent = self
+            .current
+            .borrow()
+            .parent
+            .clone()
+            .expect("attempted to remove global scope");
+        self.current = parent;
     }
 }
 
@@ -312,18 +152,42 @@ mod test {
 
     #[test]
     fn global_scope() -> Result<()> {
-        let mut env = EnvironmentTree::default();
-        env.cur_mut()
-            .define("a".into(), Some(LoxObject::Float(3.0)));
-        assert_eq!(env.cur_leaf().get("a")?, Some(LoxObject::Float(3.0)));
+        let mut env = Environment::default();
+        env.define("a", Some(LoxObject::Float(3.0)));
+        assert_eq!(env.get_global("a")?, Some(LoxObject::Float(3.0)));
+
+        env.assign_global("a", LoxObject::String("foo".into()))?;
+        assert_eq!(env.get_at(0, "a")?, Some(LoxObject::String("foo".into())));
+        assert!(env.get_global("b").is_err());
+
+        Ok(())
+    }
 
-        env.cur_mut()
-            .assign("a".into(), LoxObject::String("foo".into()))?;
-        assert_eq!(
-            env.cur_leaf().get("a")?,
-            Some(LoxObject::String("foo".into()))
-        );
+    #[test]
+    fn assign_through_scopes() -> Result<()> {
+        let mut env = Environment::default();
+        env.define("a", Some(LoxObject::Float(1.0)));
+        env.add_scope();
+        env.define("a", Some(LoxObject::Float(10.0)));
+        env.assign_at(1, "a", LoxObject::Float(2.0))?;
+        assert_eq!(env.get_at(0, "a")?, Some(LoxObject::Float(10.0)));
+        // the resolver's depth is trusted, a name that isn't there isn't looked for further out
+        assert!(env.get_at(0, "b").is_err());
+        env.remove_scope();
+        assert_eq!(env.get_global("a")?, Some(LoxObject::Float(2.0)));
+        Ok(())
+    }
 
+    #[test]
+    fn closures_share_frames() -> Result<()> {
+        let mut env = Environment::default();
+        env.add_scope();
+        env.define("i", Some(LoxObject::Float(0.0)));
+        let mut first = env.clone();
+        let second = env.clone();
+        first.add_scope();
+        first.assign_at(1, "i", LoxObject::Float(1.0))?;
+        assert_eq!(second.get_at(0, "i")?, Some(LoxObject::Float(1.0)));
         Ok(())
     }
 }
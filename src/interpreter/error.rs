@@ -1,4 +1,6 @@
+use crate::scanner::Span;
 use crate::token::SmartString;
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Debug, Clone, Error)]
@@ -13,4 +15,120 @@ pub enum RuntimeError {
 
     #[error("Expected {expected} Arguments, but got {got}.")]
     Arity { expected: usize, got: usize },
+
+    #[error("Only instances have properties.")]
+    NotInstance,
+    #[error("Undefined property '{0}'.")]
+    UndefinedProperty(SmartString),
+    #[error("Superclass must be a class.")]
+    SuperclassNotClass,
+    #[error("Stack overflow.")]
+    StackOverflow,
+    #[error("Operand must be a list.")]
+    NotList,
+    #[error("List index must be a whole number.")]
+    InvalidIndex,
+    #[error("Index {index} is out of range for a list of length {len}.")]
+    IndexOutOfRange { index: f64, len: usize },
+    #[error("Only lists and maps can be indexed.")]
+    NotIndexable,
+    #[error("Operand must be a map.")]
+    NotMap,
+    #[error("Map keys must be strings, numbers, booleans or nil.")]
+    InvalidKey,
+    #[error("Undefined key {0}.")]
+    UndefinedKey(SmartString),
+    /// From `len`, the only native that takes all three
+    #[error("Operand must be a list, map or string.")]
+    NoLength,
+
+    /// The first frame is where the error happened, the last one is the script itself
+    #[error("{error}")]
+    Traceback {
+        error: Box<RuntimeError>,
+        trace: Vec<TraceFrame>,
+    },
+}
+
+/// A function that was running when an error happened, and the span it had got to
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    /// None for top level code
+    pub function: Option<SmartString>,
+    pub span: Span,
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.function {
+            Some(function) => write!(f, "[line {}] in {}()", self.span.line, function),
+            None => write!(f, "[line {}] in script", self.span.line),
+        }
+    }
+}
+
+impl RuntimeError {
+    /// Runtime errors are numbered E05xx, wrapping one in a trace keeps its code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Traceback { error, .. } => error.code(),
+            Self::InvalidOperand => "E0501",
+            Self::Undefined(_) => "E0502",
+            Self::NotCallable => "E0503",
+            Self::Arity { .. } => "E0504",
+            Self::NotInstance => "E0505",
+            Self::UndefinedProperty(_) => "E0506",
+            Self::SuperclassNotClass => "E0507",
+            Self::StackOverflow => "E0508",
+            Self::NotList => "E0509",
+            Self::InvalidIndex => "E0510",
+            Self::IndexOutOfRange { .. } => "E0511",
+            Self::NotIndexable => "E0512",
+            Self::NotMap => "E0513",
+            Self::InvalidKey => "E0514",
+            Self::UndefinedKey(_) => "E0515",
+            Self::NoLength => "E0516",
+        }
+    }
+
+    /// Called as the error leaves a function, the frame we thought was the script becomes that
+    /// function and the caller carries on from the call it made
+    pub fn unwind(self, function: &str, call_span: Span) -> Self {
+        let (error, mut trace) = match self {
+            Self::Traceback { error, trace } => (error, trace),
+            error => (Box::new(error), Vec::new()),
+        };
+        match trace.last_mut() {
+            Some(frame) => frame.function = Some(function.into()),
+            None => trace.push(TraceFrame {
+                function: Some(function.into()),
+                span: call_span,
+            }),
+        }
+        trace.push(TraceFrame {
+            function: None,
+            span: call_span,
+        });
+        Self::Traceback { error, trace }
+    }
+
+    pub fn trace(&self) -> &[TraceFrame] {
+        match self {
+            Self::Traceback { trace, .. } => trace,
+            _ => &[],
+        }
+    }
+
+    /// Where the error actually happened, in the innermost frame
+    pub fn span(&self) -> Option<Span> {
+        self.trace().first().map(|frame| frame.span)
+    }
+
+    /// What went wrong, without the trace
+    pub fn kind(&self) -> &Self {
+        match self {
+            Self::Traceback { error, .. } => error.kind(),
+            error => error,
+        }
+    }
 }
@@ -1,20 +1,58 @@
+pub mod environment;
 pub mod error;
+pub mod natives;
 
+use crate::scanner::Span;
+use crate::syntax_trees::lox_callable::LoxCallable;
+use crate::syntax_trees::lox_class::{LoxClass, LoxInstance};
+use crate::syntax_trees::lox_list::LoxList;
+use crate::syntax_trees::lox_map::LoxMap;
 use crate::syntax_trees::lox_object::LoxObject;
 use crate::syntax_trees::statement::Function;
 use crate::syntax_trees::statement::Statement;
 use crate::token::SmartString;
-pub use error::RuntimeError;
+pub use error::{RuntimeError, TraceFrame};
 
 use crate::syntax_trees::expression::BinaryOperator;
 use crate::syntax_trees::expression::Expression;
 use crate::syntax_trees::expression::LogicalOperator;
 use crate::syntax_trees::expression::UnaryOperator;
+use crate::vm::FRAMES_MAX;
 pub use environment::Environment;
-pub use environment::Global;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 pub type Result<T> = std::result::Result<T, RuntimeError>;
 
+/// Why a statement stopped running early, unwinds up to the nearest loop or function call
+enum Jump {
+    Return(LoxObject),
+    Break,
+    Continue,
+}
+
+/// Holds a place on the call stack until it's dropped, so returns and errors give it back
+struct CallDepth(Rc<Cell<usize>>);
+
+impl CallDepth {
+    fn enter(env: &Environment, span: Span) -> Result<Self> {
+        let calls = env.calls().clone();
+        // the vm's script frame counts towards its limit, so one less is left for calls
+        if calls.get() + 1 >= FRAMES_MAX {
+            return Err(error(RuntimeError::StackOverflow, span));
+        }
+        calls.set(calls.get() + 1);
+        Ok(Self(calls))
+    }
+}
+
+impl Drop for CallDepth {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
 /*
  *
  *
@@ -29,17 +67,19 @@ pub fn interpret(statements: Vec<Statement>, env: &mut Environment) -> Result<()
     Ok(())
 }
 
-fn execute(statement: Statement, environment: &mut Environment) -> Result<Option<LoxObject>> {
+fn execute(statement: Statement, environment: &mut Environment) -> Result<Option<Jump>> {
     match statement {
-        Statement::Expression(statement) => {
-            evaluate(statement, environment)?;
+        Statement::Expression { expression, .. } => {
+            evaluate(expression, environment)?;
             Ok(None)
         }
-        Statement::Print(statement) => {
-            println!("{}", evaluate(statement, environment)?);
+        Statement::Print { expression, .. } => {
+            println!("{}", evaluate(expression, environment)?);
             Ok(None)
         }
-        Statement::Var { name, initializer } => {
+        Statement::Var {
+            name, initializer, ..
+        } => {
             let initial_value = if let Some(expression) = initializer {
                 Some(evaluate(expression, environment)?)
             } else {
@@ -52,6 +92,7 @@ fn execute(statement: Statement, environment: &mut Environment) -> Result<Option
             condition,
             then,
             else_case,
+            ..
         } => {
             if evaluate(condition, environment)?.truthy() {
                 execute(*then, environment)
@@ -61,67 +102,124 @@ fn execute(statement: Statement, environment: &mut Environment) -> Result<Option
                 Ok(None)
             }
         }
-        Statement::While { condition, body } => {
+        Statement::While {
+            condition,
+            body,
+            increment,
+            ..
+        } => {
             while evaluate(condition.clone(), environment)?.truthy() {
-                if let Some(return_value) = execute(*body.clone(), environment)? {
-                    return Ok(Some(return_value));
+                match execute(*body.clone(), environment)? {
+                    Some(Jump::Break) => break,
+                    Some(Jump::Return(value)) => return Ok(Some(Jump::Return(value))),
+                    Some(Jump::Continue) | None => {}
+                }
+                if let Some(increment) = &increment {
+                    evaluate(increment.clone(), environment)?;
                 }
             }
             Ok(None)
         }
-        Statement::Block(statements) => {
+        Statement::Block { statements, .. } => {
             environment.add_scope();
-            let ret_val = execute_block(statements, environment)?;
+            let ret_val = execute_block(statements, environment);
+            // pop the scope even on error so the REPL doesn't stay stuck inside the block
             environment.remove_scope();
-            Ok(ret_val)
+            ret_val
         }
         Statement::Function(function) => new_function(function, environment),
+        Statement::Class {
+            name,
+            superclass,
+            methods,
+            ..
+        } => {
+            let superclass = match superclass {
+                Some(expression) => {
+                    let span = expression.span();
+                    match evaluate(expression, environment)? {
+                        LoxObject::Class(class) => Some(class),
+                        _ => return Err(error(RuntimeError::SuperclassNotClass, span)),
+                    }
+                }
+                None => None,
+            };
+            let methods: HashMap<_, _> = methods
+                .into_iter()
+                .map(|method| {
+                    let name = SmartString::from(method.name.clone());
+                    (name, function_object(method, environment))
+                })
+                .collect();
+            let class = LoxClass::new(name.clone().into(), superclass, methods);
+            environment.define(name.as_ref(), Some(LoxObject::Class(Rc::new(class))));
+            Ok(None)
+        }
 
         Statement::Return { value, .. } => {
             if let Some(value) = value {
                 let value = evaluate(value, environment)?;
-                Ok(Some(value))
+                Ok(Some(Jump::Return(value)))
             } else {
-                Ok(Some(LoxObject::Nil))
+                Ok(Some(Jump::Return(LoxObject::Nil)))
             }
         }
+        Statement::Break { .. } => Ok(Some(Jump::Break)),
+        Statement::Continue { .. } => Ok(Some(Jump::Continue)),
     }
 }
 
-fn new_function(function: Function, env: &mut Environment) -> Result<Option<LoxObject>> {
+fn new_function(function: Function, env: &mut Environment) -> Result<Option<Jump>> {
     let name = function.name.to_string();
-    let function_object = if env.global() {
-        LoxObject::Function(function)
-    } else {
-        LoxObject::Closure {
-            declaration: function,
-            env: env.as_closure(),
-        }
-    };
+    let function_object = function_object(function, env);
     env.define(&name, Some(function_object));
     Ok(None)
 }
 
+fn function_object(function: Function, env: &Environment) -> LoxObject {
+    LoxObject::Closure {
+        declaration: function,
+        env: env.clone(),
+    }
+}
+
 pub fn evaluate(expression: Expression, environment: &mut Environment) -> Result<LoxObject> {
     match expression {
         Expression::Binary {
             left,
             operator,
             right,
-        } => handle_binary(*left, operator, *right, environment),
-        Expression::Grouping(inner) => evaluate(*inner, environment),
-        Expression::Literal(inner) => Ok(inner),
-        Expression::Unary { operator, inner } => handle_unary(operator, *inner, environment),
-        Expression::Variable { name, line } => handle_variable(&name, line, environment),
-        Expression::Assign { name, value } => {
+            span,
+        } => handle_binary(*left, operator, *right, environment).map_err(|e| error(e, span)),
+        Expression::Grouping { inner, .. } => evaluate(*inner, environment),
+        Expression::Literal { value, .. } => Ok(value),
+        Expression::Unary {
+            operator,
+            inner,
+            span,
+        } => handle_unary(operator, *inner, environment).map_err(|e| error(e, span)),
+        Expression::Variable { name, span, depth } => {
+            handle_variable(&name, depth, span, environment)
+        }
+        Expression::Assign {
+            name,
+            value,
+            span,
+            depth,
+        } => {
             let value = evaluate(*value, environment)?;
-            environment.assign(&name, value.clone())?;
+            match depth {
+                Some(depth) => environment.assign_at(depth, &name, value.clone()),
+                None => environment.assign_global(&name, value.clone()),
+            }
+            .map_err(|e| error(e, span))?;
             Ok(value)
         }
         Expression::Logical {
             left,
             right,
             operator,
+            ..
         } => {
             let left = evaluate(*left, environment)?;
             if operator == LogicalOperator::OR {
@@ -133,97 +231,192 @@ pub fn evaluate(expression: Expression, environment: &mut Environment) -> Result
             }
             evaluate(*right, environment)
         }
-        Expression::Call {
-            callee,
-            paren,
-            args,
-        } => {
+        Expression::Call { callee, args, span } => {
             let callable = evaluate(*callee, environment)?;
             let mut evaluated_args = Vec::new();
             for arg in args {
                 evaluated_args.push(evaluate(arg, environment)?);
             }
-            call(callable, paren.line, evaluated_args, environment)
+            call(callable, span, evaluated_args)
+        }
+        Expression::Get { object, name, span } => match evaluate(*object, environment)? {
+            LoxObject::Instance(instance) => instance.get(&name).map_err(|e| error(e, span)),
+            _ => Err(error(RuntimeError::NotInstance, span)),
+        },
+        Expression::Set {
+            object,
+            name,
+            value,
+            span,
+        } => {
+            let LoxObject::Instance(instance) = evaluate(*object, environment)? else {
+                return Err(error(RuntimeError::NotInstance, span));
+            };
+            let value = evaluate(*value, environment)?;
+            instance.set(name, value.clone());
+            Ok(value)
+        }
+        Expression::List { elements, .. } => {
+            let elements = elements
+                .into_iter()
+                .map(|element| evaluate(element, environment))
+                .collect::<Result<_>>()?;
+            Ok(LoxObject::List(LoxList::new(elements)))
+        }
+        Expression::Map { entries, span } => {
+            let entries = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    Ok((evaluate(key, environment)?, evaluate(value, environment)?))
+                })
+                .collect::<Result<_>>()?;
+            let map = LoxMap::new(entries).map_err(|e| error(e, span))?;
+            Ok(LoxObject::Map(map))
+        }
+        Expression::Index {
+            object,
+            index,
+            span,
+        } => {
+            let object = evaluate(*object, environment)?;
+            let index = evaluate(*index, environment)?;
+            object.get_index(&index).map_err(|e| error(e, span))
+        }
+        Expression::SetIndex {
+            object,
+            index,
+            value,
+            span,
+        } => {
+            let object = evaluate(*object, environment)?;
+            let index = evaluate(*index, environment)?;
+            let value = evaluate(*value, environment)?;
+            object
+                .set_index(&index, value.clone())
+                .map_err(|e| error(e, span))?;
+            Ok(value)
+        }
+        Expression::This { span, depth } => handle_variable("this", depth, span, environment),
+        Expression::Super {
+            method,
+            span,
+            depth,
+        } => {
+            // bound methods define this and super in the same scope
+            let (LoxObject::Class(superclass), LoxObject::Instance(this)) = (
+                handle_variable("super", depth, span, environment)?,
+                handle_variable("this", depth, span, environment)?,
+            ) else {
+                unreachable!("super and this are only defined by bound methods");
+            };
+            superclass
+                .bind(&method, &this)
+                .ok_or_else(|| error(RuntimeError::UndefinedProperty(method), span))
         }
     }
 }
 
-fn call(
-    callable: LoxObject,
-    line: u32,
-    args: Vec<LoxObject>,
-    env: &mut Environment,
-) -> Result<LoxObject> {
+fn call(callable: LoxObject, span: Span, args: Vec<LoxObject>) -> Result<LoxObject> {
     match callable {
-        LoxObject::Function(function) => {
-            /*
-             *  TODO: If global scope then use FunctionScope, otherwise use existing scoping rules
-             *  :3
-             *
-             */
-            check_arity(function.params.len(), args.len(), line)?;
-            env.add_scope();
-            //let mut function_env = env.function_environment();
-            for (param, arg) in function.params.iter().zip(args) {
-                let smartstr: SmartString = param.clone().into();
-                let param: String = smartstr.into();
-                env.define(&param, Some(arg))
-            }
-            let return_value = execute_block(function.body.clone(), env)?;
-            env.remove_scope();
-            match return_value {
-                Some(value) => Ok(value),
-                None => Ok(LoxObject::Nil),
-            }
-        }
         LoxObject::Closure { declaration, env } => {
-            check_arity(declaration.params.len(), args.len(), line)?;
+            check_arity(declaration.params.len(), args.len(), span)?;
+            let _depth = CallDepth::enter(&env, span)?;
 
+            // the clone shares the captured frames, add_scope only moves the clone
             let mut closure_env = env.clone();
             closure_env.add_scope();
 
             for (param, arg) in declaration.params.iter().zip(args) {
-                let smartstr: SmartString = param.clone().into();
-                let param: String = smartstr.into();
-                closure_env.define(&param, Some(arg))
+                closure_env.define(param.as_ref(), Some(arg))
             }
 
-            let return_value = execute_block(declaration.body.clone(), &mut closure_env)?;
+            let return_value = execute_block(declaration.body.clone(), &mut closure_env)
+                .map_err(|e| e.unwind(declaration.name.as_ref(), span))?;
             match return_value {
-                Some(value) => Ok(value),
-                None => Ok(LoxObject::Nil),
+                Some(Jump::Return(value)) => Ok(value),
+                // the parser keeps break and continue inside loops, so they never get this far
+                Some(Jump::Break | Jump::Continue) | None => Ok(LoxObject::Nil),
             }
         }
+        LoxObject::Native(native) => {
+            check_arity(native.arity(), args.len(), span)?;
+            native.call(args).map_err(|e| error(e, span))
+        }
+        LoxObject::BoundMethod {
+            receiver,
+            method,
+            superclass,
+        } => {
+            let LoxObject::Closure {
+                declaration,
+                env: mut closure_env,
+            } = *method
+            else {
+                unreachable!("methods are always closures");
+            };
+            // initializers always hand back the instance, even when called directly
+            let initializer = declaration.name.as_ref() == "init";
+            let this = LoxObject::Instance(receiver);
+            // this and super share the scope the method body's scope hangs off of
+            closure_env.add_scope();
+            closure_env.define("this", Some(this.clone()));
+            if let Some(superclass) = superclass {
+                closure_env.define("super", Some(LoxObject::Class(superclass)));
+            }
+            let method = LoxObject::Closure {
+                declaration,
+                env: closure_env,
+            };
+            let return_value = call(method, span, args)?;
+            Ok(if initializer { this } else { return_value })
+        }
+        LoxObject::Class(class) => {
+            check_arity(class.arity(), args.len(), span)?;
+            let instance = LoxInstance::new(class.clone());
+            if let Some(initializer) = class.bind("init", &instance) {
+                call(initializer, span, args)?;
+            }
+            Ok(LoxObject::Instance(instance))
+        }
 
-        _ => Err(error(RuntimeError::NotCallable, Some(line))),
+        _ => Err(error(RuntimeError::NotCallable, span)),
     }
 }
 
-fn check_arity(expected: usize, got: usize, line: u32) -> Result<()> {
+fn check_arity(expected: usize, got: usize, span: Span) -> Result<()> {
     if expected == got {
         Ok(())
     } else {
-        Err(error(RuntimeError::Arity { expected, got }, Some(line)))
+        Err(error(RuntimeError::Arity { expected, got }, span))
     }
 }
 
 fn execute_block(
     statements: Vec<Statement>,
     environment: &mut Environment,
-) -> Result<Option<LoxObject>> {
+) -> Result<Option<Jump>> {
     for statement in statements {
-        if let Some(return_value) = execute(statement, environment)? {
-            return Ok(Some(return_value));
+        if let Some(jump) = execute(statement, environment)? {
+            return Ok(Some(jump));
         }
     }
     Ok(None)
 }
 
-fn handle_variable(key: &str, line: u32, environment: &mut Environment) -> Result<LoxObject> {
-    match environment.get(key) {
+fn handle_variable(
+    key: &str,
+    depth: Option<usize>,
+    span: Span,
+    environment: &mut Environment,
+) -> Result<LoxObject> {
+    let value = match depth {
+        Some(depth) => environment.get_at(depth, key),
+        None => environment.get_global(key),
+    };
+    match value {
         Ok(None) => Ok(LoxObject::Nil),
         Ok(Some(object)) => Ok(object.clone()),
-        Err(e) => Err(error(e, Some(line))),
+        Err(e) => Err(error(e, span)),
     }
 }
 
@@ -241,32 +434,22 @@ fn handle_binary(
     let left = evaluate(left, environment)?;
     let right = evaluate(right, environment)?;
 
-    // can_compare does the typecheck so that we throw invalidOperand when comparing instead of
-    // returning false
+    // can_compare does the typecheck so that we throw invalidOperand when ordering values that
+    // can't be ordered instead of returning false, equality works on anything
     let can_compare = left.partial_cmp(&right).is_some();
-    let line = match operator {
-        PLUS(line) | MINUS(line) | STAR(line) | SLASH(line) | GREATER(line)
-        | GREATEREQUAL(line) | LESS(line) | LESSEQUAL(line) | EQUALEQUAL(line)
-        | BANGEQUAL(line) => line,
-    };
     // worst line of code ever written
-    let res = match operator {
-        PLUS(_) => left + right,
-        MINUS(_) => left - right,
-        STAR(_) => left * right,
-        SLASH(_) => left / right,
-        GREATER(_) if can_compare => Ok(Bool(left > right)),
-        GREATEREQUAL(_) if can_compare => Ok(Bool(left >= right)),
-        LESS(_) if can_compare => Ok(Bool(left < right)),
-        LESSEQUAL(_) if can_compare => Ok(Bool(left <= right)),
-        EQUALEQUAL(_) if can_compare => Ok(Bool(left == right)),
-        BANGEQUAL(_) if can_compare => Ok(Bool(left != right)),
+    match operator {
+        PLUS => left + right,
+        MINUS => left - right,
+        STAR => left * right,
+        SLASH => left / right,
+        GREATER if can_compare => Ok(Bool(left > right)),
+        GREATEREQUAL if can_compare => Ok(Bool(left >= right)),
+        LESS if can_compare => Ok(Bool(left < right)),
+        LESSEQUAL if can_compare => Ok(Bool(left <= right)),
+        EQUALEQUAL => Ok(Bool(left == right)),
+        BANGEQUAL => Ok(Bool(left != right)),
         _ => Err(RuntimeError::InvalidOperand),
-    };
-    if let Err(e) = res {
-        Err(error(e, Some(line)))
-    } else {
-        res
     }
 }
 
@@ -282,221 +465,17 @@ fn handle_unary(
     }
 }
 
-fn error(error: RuntimeError, line: Option<u32>) -> RuntimeError {
-    match line {
-        Some(line) => {
-            println!("Error on line {}: {}", line, error)
-        }
-        None => {
-            println!("Error at end of file: {}", error)
-        }
-    };
-    error
-}
-
-pub mod environment {
-    use super::LoxObject;
-    use super::RuntimeError;
-    use crate::token::SmartString;
-    use std::collections::HashMap;
-    use std::fmt;
-
-    use super::Result;
-    type Env = HashMap<SmartString, Option<LoxObject>>;
-
-    pub trait Global: fmt::Debug {
-        fn get(&self, key: &str) -> Option<&Option<LoxObject>>;
-
-        fn insert(&mut self, key: &str, value: Option<LoxObject>) -> Option<()>;
-        fn define(&mut self, key: &str, value: Option<LoxObject>) {
-            self.insert(key, value);
-        }
-        fn assign(&mut self, key: &str, value: LoxObject) -> Result<()> {
-            self.insert(key, Some(value))
-                .ok_or(RuntimeError::Undefined(key.into()))
-        }
-
-        fn as_env(&self) -> &Env;
-        fn as_mut_env(&mut self) -> &mut Env;
-    }
-
-    #[derive(Clone, Debug, Default)]
-    pub struct Environment {
-        inner: Option<EnvironmentInner>,
-        global: Env,
-    }
-
-    impl fmt::Display for Environment {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "{:?}", self.global)?;
-            if let Some(inner) = &self.inner {
-                write!(f, "|{}", inner)
-            } else {
-                Ok(())
-            }
-        }
-    }
-
-    #[derive(Clone, Debug)]
-    struct EnvironmentInner {
-        env: Env,
-        parent: Option<Box<EnvironmentInner>>,
-    }
-
-    impl fmt::Display for EnvironmentInner {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            if let Some(parent) = &self.parent {
-                write!(f, "|{parent}|\n|{env:?}\n", env = self.env)
-            } else {
-                writeln!(f, "{env:?}", env = self.env)
-            }
-        }
-    }
-
-    impl Global for Env {
-        fn get(&self, key: &str) -> Option<&Option<LoxObject>> {
-            HashMap::get(self, key)
-        }
-
-        fn insert(&mut self, key: &str, value: Option<LoxObject>) -> Option<()> {
-            self.insert(key.into(), value)?;
-            Some(())
-        }
-
-        fn as_env(&self) -> &Self {
-            self
-        }
-
-        fn as_mut_env(&mut self) -> &mut Self {
-            self
-        }
-    }
-
-    impl Global for &mut Env {
-        fn get(&self, key: &str) -> Option<&Option<LoxObject>> {
-            HashMap::get(self, key)
-        }
-
-        fn insert(&mut self, key: &str, value: Option<LoxObject>) -> Option<()> {
-            HashMap::insert(self, key.into(), value)?;
-            Some(())
-        }
-
-        fn as_env(&self) -> &Env {
-            self
-        }
-
-        fn as_mut_env(&mut self) -> &mut Env {
-            self
-        }
-    }
-
-    impl Environment {
-        pub const fn global(&self) -> bool {
-            self.inner.is_none()
-        }
-
-        pub fn get(&self, key: &str) -> Result<&Option<LoxObject>> {
-            let Some(ref inner) = self.inner else {
-                return self.get_global(key);
-            };
-
-            if let Some(value) = inner.get(key) {
-                Ok(value)
-            } else {
-                self.get_global(key)
-            }
-        }
-
-        pub fn get_global(&self, key: &str) -> Result<&Option<LoxObject>> {
-            match self.global.get(key) {
-                Some(value) => Ok(value),
-                None => Err(RuntimeError::Undefined(key.into())),
-            }
-        }
-
-        pub fn define(&mut self, key: &str, value: Option<LoxObject>) {
-            if let Some(ref mut inner) = self.inner {
-                inner.define(key, value);
-            } else {
-                self.global.define(key, value);
-            }
-        }
-
-        pub fn assign(&mut self, key: &str, value: LoxObject) -> Result<()> {
-            if let Some(ref mut inner) = self.inner {
-                inner.assign(key, value)?;
-            } else {
-                self.global.assign(key, value)?;
-            }
-            Ok(())
-        }
-
-        pub fn add_scope(&mut self) {
-            if let Some(ref mut inner) = self.inner {
-                inner.add_scope();
-            } else {
-                self.inner = Some(EnvironmentInner::new());
-            }
-        }
-
-        pub fn remove_scope(&mut self) {
-            let Some(inner) = self.inner.clone() else {
-                panic!("attempted to remove global scope");
-            };
-            self.inner = inner.parent.map(|env| *env)
-        }
-        pub fn as_closure(&self) -> Self {
-            self.clone()
-        }
-    }
-
-    impl EnvironmentInner {
-        fn new() -> Self {
-            Self {
-                env: Env::new(),
-                parent: None,
-            }
-        }
-
-        pub fn get_mut(&mut self, key: &str) -> Option<&mut Option<LoxObject>> {
-            if let Some(value) = self.env.get_mut(key) {
-                Some(value)
-            } else if let Some(parent) = &mut self.parent {
-                parent.get_mut(key)
-            } else {
-                None
-            }
-        }
-
-        fn get(&self, key: &str) -> Option<&Option<LoxObject>> {
-            if let Some(value) = self.env.get(key) {
-                Some(value)
-            } else if let Some(parent) = &self.parent {
-                parent.get(key)
-            } else {
-                None
-            }
-        }
-
-        pub fn assign(&mut self, key: &str, value: LoxObject) -> Result<()> {
-            if let Some(variable) = self.get_mut(key) {
-                *variable = Some(value);
-                Ok(())
-            } else {
-                Err(RuntimeError::Undefined(key.into()))
-            }
-        }
-
-        pub fn define(&mut self, key: &str, value: Option<LoxObject>) {
-            self.env.insert(key.into(), value);
-        }
-
-        fn add_scope(&mut self) {
-            let env = Env::new();
-            let parent = Box::new(self.clone());
-            self.env = env;
-            self.parent = Some(parent);
-        }
+/// Attaches the span the error happened at, the stack trace then gets filled in as the error
+/// unwinds back out through each call. Errors that already have a trace keep their innermost span
+pub(crate) fn error(error: RuntimeError, span: Span) -> RuntimeError {
+    match error {
+        error @ RuntimeError::Traceback { .. } => error,
+        error => RuntimeError::Traceback {
+            error: Box::new(error),
+            trace: vec![TraceFrame {
+                function: None,
+                span,
+            }],
+        },
     }
 }
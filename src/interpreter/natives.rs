@@ -0,0 +1,149 @@
+use super::{Environment, Result, RuntimeError};
+use crate::syntax_trees::lox_callable::{LoxCallable, NativeFunction};
+use crate::syntax_trees::lox_list::LoxList;
+use crate::syntax_trees::lox_map::LoxMap;
+use crate::syntax_trees::lox_object::LoxObject;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Defines the builtin functions in the global scope, call before running any code
+pub fn define(env: &mut Environment) {
+    for native in natives() {
+        let name = native.name().to_owned();
+        env.define(&name, Some(LoxObject::Native(native)));
+    }
+}
+
+/// Every builtin function, shared by both backends
+pub fn natives() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction::new("clock", 0, clock),
+        NativeFunction::new("len", 1, len),
+        NativeFunction::new("push", 2, push),
+        NativeFunction::new("pop", 1, pop),
+        NativeFunction::new("insert", 3, insert),
+        NativeFunction::new("remove", 2, remove),
+        NativeFunction::new("keys", 1, keys),
+        NativeFunction::new("values", 1, values),
+        NativeFunction::new("has", 2, has),
+        NativeFunction::new("delete", 2, delete),
+    ]
+}
+
+fn clock(_args: Vec<LoxObject>) -> Result<LoxObject> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(LoxObject::Float(elapsed.as_secs_f64()))
+}
+
+fn len(args: Vec<LoxObject>) -> Result<LoxObject> {
+    match &args[0] {
+        LoxObject::List(list) => Ok(LoxObject::Float(list.len() as f64)),
+        LoxObject::Map(map) => Ok(LoxObject::Float(map.len() as f64)),
+        LoxObject::String(string) => Ok(LoxObject::Float(string.chars().count() as f64)),
+        _ => Err(RuntimeError::NoLength),
+    }
+}
+
+fn push(args: Vec<LoxObject>) -> Result<LoxObject> {
+    let [list, value] = <[LoxObject; 2]>::try_from(args).expect("arity is checked by the caller");
+    as_list(&list)?.push(value);
+    Ok(LoxObject::Nil)
+}
+
+fn pop(args: Vec<LoxObject>) -> Result<LoxObject> {
+    Ok(as_list(&args[0])?.pop())
+}
+
+fn insert(args: Vec<LoxObject>) -> Result<LoxObject> {
+    let [list, index, value] =
+        <[LoxObject; 3]>::try_from(args).expect("arity is checked by the caller");
+    as_list(&list)?.insert(&index, value)?;
+    Ok(LoxObject::Nil)
+}
+
+fn remove(args: Vec<LoxObject>) -> Result<LoxObject> {
+    as_list(&args[0])?.remove(&args[1])
+}
+
+fn as_list(object: &LoxObject) -> Result<&LoxList> {
+    match object {
+        LoxObject::List(list) => Ok(list),
+        _ => Err(RuntimeError::NotList),
+    }
+}
+
+fn keys(args: Vec<LoxObject>) -> Result<LoxObject> {
+    Ok(LoxObject::List(LoxList::new(as_map(&args[0])?.keys())))
+}
+
+fn values(args: Vec<LoxObject>) -> Result<LoxObject> {
+    Ok(LoxObject::List(LoxList::new(as_map(&args[0])?.values())))
+}
+
+fn has(args: Vec<LoxObject>) -> Result<LoxObject> {
+    Ok(LoxObject::Bool(as_map(&args[0])?.has(&args[1])?))
+}
+
+fn delete(args: Vec<LoxObject>) -> Result<LoxObject> {
+    as_map(&args[0])?.delete(&args[1])
+}
+
+fn as_map(object: &LoxObject) -> Result<&LoxMap> {
+    match object {
+        LoxObject::Map(map) => Ok(map),
+        _ => Err(RuntimeError::NotMap),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::vm::Vm;
+    use crate::Interpreter;
+
+    #[test]
+    fn clock_counts_up() {
+        let mut last = 0.0;
+        for _ in 0..100 {
+            let Ok(LoxObject::Float(now)) = clock(Vec::new()) else {
+                panic!("clock should give back a number");
+            };
+            assert!(now >= last, "{now} came after {last}");
+            last = now;
+        }
+    }
+
+    #[test]
+    fn len_of_something_without_one() {
+        let Err(e) = len(vec![LoxObject::Float(1.0)]) else {
+            panic!("numbers have no length");
+        };
+        assert!(matches!(e, RuntimeError::NoLength));
+        assert_eq!(e.to_string(), "Operand must be a list, map or string.");
+    }
+
+    #[test]
+    fn wrong_argument_count() {
+        for (source, expected, got) in [("clock(1);", 0, 1), ("push([]);", 2, 1)] {
+            let results = [
+                Interpreter::new().eval(source).map(|_| ()),
+                Vm::new().eval(source),
+            ];
+            for result in results {
+                let Err(Error::RuntimeError(e)) = result else {
+                    panic!("{source:?} should have failed to run");
+                };
+                let RuntimeError::Arity {
+                    expected: wanted,
+                    got: given,
+                } = e.kind()
+                else {
+                    panic!("{source:?} gave {e}");
+                };
+                assert_eq!((*wanted, *given), (expected, got), "{source:?}");
+            }
+        }
+    }
+}
@@ -1,61 +1,238 @@
-use std::fs::File;
-use std::io::{stdin, stdout, Read, Write};
+use std::fs;
+use std::io::{self, stdin, Read};
 
+pub mod cli;
+pub mod diagnostics;
+mod embed;
 pub mod error;
+pub mod formatter;
 pub mod interpreter;
 pub mod parser;
+mod repl;
+pub mod resolver;
 pub mod scanner;
 pub mod syntax_trees;
 pub mod token;
+pub mod vm;
 
-use interpreter::Environment;
+pub use embed::Interpreter;
+pub use repl::run_prompt;
 
+use cli::{Cli, Command, Script};
+use diagnostics::{ErrorFormat, Reporter};
 use error::Error;
-use parser::Parser;
-
-pub fn run_file(file_name: &str) -> Result<(), Error> {
-    let mut file = File::open(file_name).unwrap();
-    let mut contents: String = String::new();
-    file.read_to_string(&mut contents).unwrap();
-    let contents = contents.into_boxed_str();
-    let mut env = Environment::default();
-    run(contents, &mut env)
-}
-
-pub fn run_prompt() -> Result<(), Error> {
-    let mut workhorse = String::new();
-    let mut contents: Box<str>;
-    let mut environment = Environment::default();
-    loop {
-        print!("> ");
-        stdout().flush()?;
-        if stdin().read_line(&mut workhorse).is_ok_and(|x| x == 0) {
-            return Ok(());
+use syntax_trees::export::{self, AstFormat};
+use syntax_trees::expression::Expression;
+use syntax_trees::lox_list::LoxList;
+use syntax_trees::lox_object::LoxObject;
+use token::SmartString;
+use vm::Vm;
+
+/// Which implementation runs the program, both should behave identically
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    #[default]
+    TreeWalker,
+    Vm,
+}
+
+/// The stack the tree-walker should be run with. Every Lox call it makes goes through a good few
+/// Rust frames, so reaching the call depth limit takes far more than a main thread gets
+pub const TREE_WALKER_STACK: usize = 256 * 1024 * 1024;
+
+enum Session {
+    TreeWalker(Interpreter),
+    Vm(Vm),
+}
+
+impl Session {
+    fn new(backend: Backend) -> Self {
+        match backend {
+            Backend::TreeWalker => Self::TreeWalker(Interpreter::new()),
+            Backend::Vm => Self::Vm(Vm::new()),
         }
-        contents = workhorse.clone().into();
-        if let Err(e) = run(contents, &mut environment) {
-            println!("{}", e);
-            stdout().flush()?;
+    }
+
+    /// Hands a script the rest of the command line as the global `args`, a list of strings
+    fn define_args(&mut self, args: &[String]) {
+        let args = args
+            .iter()
+            .map(|arg| LoxObject::String(arg.as_str().into()))
+            .collect();
+        let args = LoxObject::List(LoxList::new(args));
+        match self {
+            Self::TreeWalker(interpreter) => interpreter.define_global("args", args),
+            Self::Vm(vm) => vm.define_global("args", args),
         }
-        workhorse.clear();
+    }
+
+    fn run(&mut self, source: &str, reporter: &Reporter) -> Result<(), Error> {
+        match self {
+            Self::TreeWalker(interpreter) => interpreter.eval(source).map(|_| ()),
+            Self::Vm(vm) => vm.eval(source),
+        }
+        .inspect_err(|e| reporter.report(e, source))
+    }
+
+    fn globals(&self) -> Vec<(SmartString, LoxObject)> {
+        match self {
+            Self::TreeWalker(interpreter) => interpreter.globals(),
+            Self::Vm(vm) => vm.globals(),
+        }
+    }
+
+    fn evaluate(
+        &mut self,
+        expression: Expression,
+        source: &str,
+        reporter: &Reporter,
+    ) -> Result<LoxObject, Error> {
+        match self {
+            Self::TreeWalker(interpreter) => interpreter.evaluate(expression),
+            Self::Vm(vm) => vm.evaluate(expression),
+        }
+        .inspect_err(|e| reporter.report(e, source))
     }
 }
 
-fn run(code: Box<str>, env: &mut Environment) -> Result<(), Error> {
-    if !validate(&code) {
-        return Err(Error::NotAscii);
+/// Runs the script at the path, or whatever's piped in if the path is `-`. Errors in the script
+/// have already been reported by the time this returns
+pub fn run_file(path: &str, backend: Backend, format: ErrorFormat) -> Result<(), Error> {
+    let command = Command::Run {
+        script: Script::File(path.to_string()),
+        args: Vec::new(),
     };
-    let tokens = scanner::scan(&code)?;
-    let mut parser = Parser::new(tokens);
-    let statements = parser.parse()?;
-
-    match interpreter::interpret(statements, env) {
-        Ok(()) => Ok(()),
-        Err(e) => Err(Error::RuntimeError(e)),
+    Cli {
+        command,
+        backend,
+        format,
     }
+    .run()
+}
+
+/// Scans, parses and resolves the source without running any of it
+pub fn check(source: &str) -> Result<(), Error> {
+    let mut statements = parser::parse(source)?;
+    resolver::resolve(&mut statements)?;
+    Ok(())
+}
+
+/// What the scanner makes of the source, a token per line
+pub fn dump_tokens(source: &str) -> Result<String, Error> {
+    let tokens = scanner::scan(source)?;
+    Ok(tokens.iter().map(|token| format!("{token}\n")).collect())
+}
+
+/// What the parser makes of the source, in whichever format
+pub fn dump_ast(source: &str, format: AstFormat) -> Result<String, Error> {
+    let statements = parser::parse(source)?;
+    Ok(match format {
+        AstFormat::Text => statements
+            .iter()
+            .map(|statement| format!("{statement}\n"))
+            .collect(),
+        AstFormat::Json => export::to_json(&statements) + "\n",
+        AstFormat::Sexpr => export::to_sexpr(&statements),
+    })
 }
 
-// TODO
-fn validate(_code: &str) -> bool {
-    true
+// io errors don't say what they were doing, so the path gets added to the message
+fn read_source(path: &str) -> Result<String, Error> {
+    let contents = if path == "-" {
+        let mut contents = String::new();
+        stdin().read_to_string(&mut contents).map(|_| contents)
+    } else {
+        fs::read_to_string(path)
+    };
+    contents.map_err(|e| Error::IO(io::Error::new(e.kind(), format!("{path}: {e}"))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interpreter::RuntimeError;
+
+    #[test]
+    fn missing_file() {
+        let Err(Error::IO(e)) = read_source("no/such/script.lox") else {
+            panic!("expected an io error");
+        };
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().starts_with("no/such/script.lox: "));
+    }
+
+    #[test]
+    fn script_args() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut session = Session::new(backend);
+            session.define_args(&["a".to_string(), "b c".to_string()]);
+            session
+                .run("var joined = args[0] + args[1];", &Reporter::default())
+                .unwrap();
+            let globals = session.globals();
+            let (_, joined) = globals.iter().find(|(name, _)| *name == "joined").unwrap();
+            assert_eq!(joined.to_string(), "ab c", "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let traces = [Backend::TreeWalker, Backend::Vm].map(|backend| {
+            let overflow = move || {
+                let mut session = Session::new(backend);
+                let source = "fun r(n) { return r(n + 1); }\nr(0);";
+                let Err(Error::RuntimeError(e)) = session.run(source, &Reporter::default()) else {
+                    panic!("{backend:?} should have overflowed");
+                };
+                assert!(matches!(e.kind(), RuntimeError::StackOverflow));
+                e.trace()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+            };
+            std::thread::Builder::new()
+                .stack_size(TREE_WALKER_STACK)
+                .spawn(overflow)
+                .unwrap()
+                .join()
+                .unwrap()
+        });
+        assert_eq!(traces[0].len(), vm::FRAMES_MAX);
+        assert_eq!(traces[0], traces[1]);
+    }
+
+    // print goes straight to stdout, so this compares what it would show
+    #[test]
+    fn function_display() {
+        let source = "fun f() {} class A { m() {} } fun outer() { fun inner() {} return inner; }
+            var shown = [f, clock, A, A().m, outer()];";
+        let shown = [Backend::TreeWalker, Backend::Vm].map(|backend| {
+            let mut session = Session::new(backend);
+            session.run(source, &Reporter::default()).unwrap();
+            let globals = session.globals();
+            let (_, shown) = globals.iter().find(|(name, _)| *name == "shown").unwrap();
+            shown.to_string()
+        });
+        assert_eq!(shown[0], "[<fn f>, <native fn>, A, <fn m>, <fn inner>]");
+        assert_eq!(shown[0], shown[1]);
+    }
+
+    #[test]
+    fn dumps() {
+        assert_eq!(
+            dump_tokens("print 1;").unwrap(),
+            "1:1: PRINT\n1:7: NUMBER(1)\n1:8: SEMICOLON\n"
+        );
+        assert_eq!(
+            dump_ast("print 1 + 2;", AstFormat::Text).unwrap(),
+            "print (1+2)\n"
+        );
+        assert_eq!(
+            dump_ast("print 1 + 2;", AstFormat::Sexpr).unwrap(),
+            "(print (+ 1 2))\n"
+        );
+        assert!(check("fun f() { return; }").is_ok());
+        // resolving is the only thing that catches this one
+        assert!(matches!(check("return 1;"), Err(Error::ResolvingError(_))));
+    }
 }
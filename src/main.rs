@@ -1,12 +1,36 @@
+use lox::cli::Cli;
+use lox::diagnostics::Reporter;
 use lox::error::Error;
-use std::cmp::Ordering;
+use lox::{Backend, TREE_WALKER_STACK};
 use std::env;
+use std::panic;
+use std::process::ExitCode;
+use std::thread;
 
-fn main() -> Result<(), Error> {
-    let args: Vec<String> = env::args().collect();
-    match args.len().cmp(&2) {
-        Ordering::Less => lox::run_prompt(),
-        Ordering::Equal => lox::run_file(&args[1]),
-        Ordering::Greater => Err(Error::Usage),
+fn main() -> ExitCode {
+    let cli = Cli::parse(env::args().skip(1));
+    // without a format to go on, usage errors come out readable
+    let format = cli.as_ref().map(|cli| cli.format).unwrap_or_default();
+    let tree_walker = cli
+        .as_ref()
+        .is_ok_and(|cli| cli.backend == Backend::TreeWalker);
+    let run = move || match cli.and_then(|cli| cli.run()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // errors from the script itself were reported as they happened
+            if matches!(e, Error::Usage | Error::IO(_) | Error::Unformatted(_)) {
+                Reporter::new(format, None).report(&e, "");
+            }
+            ExitCode::from(e.exit_code())
+        }
+    };
+    if !tree_walker {
+        return run();
     }
+    thread::Builder::new()
+        .stack_size(TREE_WALKER_STACK)
+        .spawn(run)
+        .expect("couldn't start the interpreter thread")
+        .join()
+        .unwrap_or_else(|panicked| panic::resume_unwind(panicked))
 }
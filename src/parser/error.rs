@@ -1,4 +1,5 @@
 use super::FunctionKind;
+use crate::scanner::Span;
 use thiserror::Error;
 
 #[derive(Debug, Clone, Error)]
@@ -46,6 +47,8 @@ pub enum ParsingError {
 
     #[error("Can't have more than 255 Arguments")]
     TooManyArgs,
+    #[error("Can't have more than 255 Parameters")]
+    TooManyParams,
 
     #[error("expected {0} name")]
     ExpectedFn(FunctionKind),
@@ -59,4 +62,108 @@ pub enum ParsingError {
     FnNoBraceOpen(FunctionKind),
     #[error("Expected '}}' after {0} body")]
     FnNoBraceClosed(FunctionKind),
+
+    #[error("Expected '{{' before class body.")]
+    ClassNoBraceOpen,
+    #[error("Expected '}}' after class body.")]
+    ClassNoBraceClosed,
+
+    #[error("Expected property name after '.'.")]
+    NoPropertyName,
+
+    #[error("Expected superclass name.")]
+    NoSuperclassName,
+    #[error("A class can't inherit from itself.")]
+    InheritsSelf,
+    #[error("Expected '.' after 'super'.")]
+    SuperNoDot,
+    #[error("Expected superclass method name.")]
+    SuperNoMethod,
+    #[error("Can't use 'super' outside of a class.")]
+    SuperOutsideClass,
+    #[error("Can't use 'super' in a class with no superclass.")]
+    SuperWithoutSuperclass,
+
+    #[error("Expected ']' after list elements.")]
+    UntermList,
+    #[error("Expected ']' after index.")]
+    UntermIndex,
+    #[error("Expected ':' after map key.")]
+    MapNoColon,
+    #[error("Expected '}}' after map entries.")]
+    UntermMap,
+
+    #[error("Can't use 'break' outside of a loop.")]
+    BreakOutsideLoop,
+    #[error("Can't use 'continue' outside of a loop.")]
+    ContinueOutsideLoop,
+
+    /// Any of the above, along with where it happened
+    #[error("{error}")]
+    At {
+        error: Box<ParsingError>,
+        span: Span,
+    },
+}
+
+impl ParsingError {
+    /// Parsing errors are numbered E02xx
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::At { error, .. } => error.code(),
+            Self::UntermParen => "E0201",
+            Self::NoExpr => "E0202",
+            Self::NoSemi => "E0203",
+            Self::NoVarName => "E0204",
+            Self::Invalid => "E0205",
+            Self::NoIdentifier => "E0206",
+            Self::InvalidAssignment => "E0207",
+            Self::UntermBrace => "E0208",
+            Self::IfParenOpen => "E0209",
+            Self::IfParenClosed => "E0210",
+            Self::WhileParenOpen => "E0211",
+            Self::WhileParenClosed => "E0212",
+            Self::ForParenOpen => "E0213",
+            Self::ForParenClosed => "E0214",
+            Self::ConditionNoSemi => "E0215",
+            Self::FnNoCloseParen => "E0216",
+            Self::TooManyArgs => "E0217",
+            Self::TooManyParams => "E0218",
+            Self::ExpectedFn(_) => "E0219",
+            Self::FnParenOpen(_) => "E0220",
+            Self::FnParenClosed(_) => "E0221",
+            Self::FnNoBraceOpen(_) => "E0222",
+            Self::FnNoBraceClosed(_) => "E0223",
+            Self::ClassNoBraceOpen => "E0224",
+            Self::ClassNoBraceClosed => "E0225",
+            Self::NoPropertyName => "E0226",
+            Self::NoSuperclassName => "E0227",
+            Self::InheritsSelf => "E0228",
+            Self::SuperNoDot => "E0229",
+            Self::SuperNoMethod => "E0230",
+            Self::SuperOutsideClass => "E0231",
+            Self::SuperWithoutSuperclass => "E0232",
+            Self::UntermList => "E0233",
+            Self::UntermIndex => "E0234",
+            Self::MapNoColon => "E0235",
+            Self::UntermMap => "E0236",
+            Self::BreakOutsideLoop => "E0237",
+            Self::ContinueOutsideLoop => "E0238",
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::At { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// What went wrong, without the location
+    pub fn kind(&self) -> &Self {
+        match self {
+            Self::At { error, .. } => error.kind(),
+            error => error,
+        }
+    }
 }
@@ -2,7 +2,8 @@
 pub mod error;
 pub use error::ParsingError;
 //use crate::scanner::{TokenType::{*,self}, Token};
-use crate::scanner::ScannedToken;
+use crate::error::Error;
+use crate::scanner::{self, Comment, ScannedToken, ScanningError, Span};
 use crate::syntax_trees::expression::{BinaryOperator, Expression, LogicalOperator, UnaryOperator};
 use crate::syntax_trees::lox_object::LoxObject;
 use crate::syntax_trees::statement::Function;
@@ -13,8 +14,69 @@ use crate::token::TokenDiscriminant;
 use std::fmt;
 use std::iter::Peekable;
 
+/// Scans and parses the source, parsing whatever did scan so that every scanning and parsing
+/// error comes back together, in the order they appear
+pub fn parse(source: &str) -> std::result::Result<Vec<Statement>, Error> {
+    parse_with_comments(source).map(|(statements, _)| statements)
+}
+
+/// Like [`parse`], but holds on to the comments as well
+pub fn parse_with_comments(
+    source: &str,
+) -> std::result::Result<(Vec<Statement>, Vec<Comment>), Error> {
+    let scanned = scanner::scan_with_comments(source);
+    let parsed = Parser::new(scanned.tokens).parse();
+    if scanned.errors.is_empty() {
+        return Ok((parsed?, scanned.comments));
+    }
+    // a token the scanner couldn't make leaves a gap the parser trips over, and those errors
+    // would only be repeating the scanning error on that line
+    let bad: Vec<Span> = scanned
+        .errors
+        .iter()
+        .filter_map(ScanningError::span)
+        .collect();
+    let fallout = |span: Span| {
+        bad.iter()
+            .any(|bad| span.line == bad.line || (bad.start..=bad.end).contains(&span.start))
+    };
+    let mut errors: Vec<(Span, Error)> = scanned
+        .errors
+        .into_iter()
+        .map(|e| (e.span().unwrap_or_default(), e.into()))
+        .collect();
+    for e in parsed.err().into_iter().flatten() {
+        let span = e.span().unwrap_or_default();
+        if !fallout(span) {
+            errors.push((span, e.into()));
+        }
+    }
+    errors.sort_by_key(|(span, _)| span.start);
+    Err(errors
+        .into_iter()
+        .map(|(_, e)| e)
+        .collect::<Vec<_>>()
+        .into())
+}
+
 pub struct Parser {
     iter: Peekable<<Vec<ScannedToken> as IntoIterator>::IntoIter>,
+    current_class: ClassKind,
+    // how many loops we're nested in within the current function, break and continue need one
+    loop_depth: usize,
+    // just past the last token, where errors about running out of input point
+    eof: Span,
+    // everything that went wrong so far, parsing carries on after an error so one run can
+    // report all of them
+    errors: Vec<ParsingError>,
+}
+
+// tracks whether we're inside a class body so misplaced `super` can be caught while parsing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -40,53 +102,137 @@ type Result<T> = std::result::Result<T, ParsingError>;
 
 impl Parser {
     pub fn new(tokens: Vec<ScannedToken>) -> Self {
+        let eof = tokens
+            .last()
+            .map_or(Span::new(1, 1, 0, 0), |last| last.span.after());
         let iter = tokens.into_iter().peekable();
-        Self { iter }
+        Self {
+            iter,
+            current_class: ClassKind::None,
+            loop_depth: 0,
+            eof,
+            errors: Vec::new(),
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Statement>> {
+    /// Gives back every error in the order they were found if anything went wrong
+    pub fn parse(&mut self) -> std::result::Result<Vec<Statement>, Vec<ParsingError>> {
         let mut statements = Vec::new();
-        let mut last_error = None;
         while self.iter.peek().is_some() {
-            // discriminant so we can cheaply pass it around
-            match self.declaration() {
-                Ok(statement) => statements.push(statement),
-                Err(e) => {
-                    last_error = Some(e);
-                }
-            }
+            statements.extend(self.declaration());
+        }
+
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
         }
+    }
 
-        match last_error {
-            Some(e) => Err(e),
-            None => Ok(statements),
+    /// Parses input that should be a single expression, optionally followed by a semicolon
+    pub fn parse_expression(&mut self) -> std::result::Result<Expression, Vec<ParsingError>> {
+        let expression = self.expression().map_err(|e| vec![e])?;
+        self.next_if(Token::SEMICOLON);
+        match self.iter.peek() {
+            None => Ok(expression),
+            Some(token) => Err(vec![Self::error(ParsingError::NoSemi, token.span)]),
         }
     }
 
-    fn declaration(&mut self) -> Result<Statement> {
-        let result = if self.iter.next_if(|x| x.type_ == Token::VAR).is_some() {
-            self.var_declaration()
-        } else if self.iter.next_if(|x| x.type_ == Token::FUN).is_some() {
-            self.function(FunctionKind::Function)
+    /// Errors are recorded rather than returned, skipping ahead to the next statement
+    fn declaration(&mut self) -> Option<Statement> {
+        let result = if let Some(keyword) = self.next_if(Token::VAR) {
+            self.var_declaration(keyword.span)
+        } else if let Some(keyword) = self.next_if(Token::FUN) {
+            self.function(FunctionKind::Function, Some(keyword.span))
+                .map(Statement::Function)
+        } else if let Some(keyword) = self.next_if(Token::CLASS) {
+            self.class_declaration(keyword.span)
         } else {
             self.statement()
         };
-        if result.is_err() {
-            self._synchronize();
+        match result {
+            Ok(statement) => Some(statement),
+            Err(e) => {
+                self.errors.push(e);
+                self._synchronize();
+                None
+            }
         }
-        result
     }
 
-    fn get_identifier(&mut self) -> Result<Identifier> {
-        let Some(next) = self.iter.next() else {
-            return Err(ParsingError::NoIdentifier);
+    fn get_identifier(&mut self) -> Result<(Identifier, Span)> {
+        match self.next_if(TokenDiscriminant::IDENTIFIER) {
+            Some(ScannedToken {
+                type_: Token::IDENTIFIER(name),
+                span,
+            }) => Ok((name.into(), span)),
+            _ => Err(Self::error(ParsingError::NoIdentifier, self.peek_span())),
+        }
+    }
+
+    fn class_declaration(&mut self, keyword: Span) -> Result<Statement> {
+        let (name, _) = self.get_identifier()?;
+
+        let superclass = if let Some(less) = self.next_if(Token::LESS) {
+            let Some(ScannedToken {
+                type_: Token::IDENTIFIER(superclass),
+                span,
+            }) = self.next_if(TokenDiscriminant::IDENTIFIER)
+            else {
+                return Err(Self::error(
+                    ParsingError::NoSuperclassName,
+                    less.span.after(),
+                ));
+            };
+            if superclass == name.as_ref() {
+                return Err(Self::error(ParsingError::InheritsSelf, span));
+            }
+            Some(Expression::Variable {
+                name: superclass,
+                span,
+                depth: None,
+            })
+        } else {
+            None
         };
-        let res: std::result::Result<Identifier, ()> = next.type_.try_into();
-        res.map_err(|_| ParsingError::NoIdentifier)
+
+        self.consume(Token::LEFTBRACE, ParsingError::ClassNoBraceOpen)?;
+
+        let enclosing = self.current_class;
+        self.current_class = if superclass.is_some() {
+            ClassKind::Subclass
+        } else {
+            ClassKind::Class
+        };
+        let methods = self.methods();
+        self.current_class = enclosing;
+        let methods = methods?;
+
+        let close = self.consume(Token::RIGHTBRACE, ParsingError::ClassNoBraceClosed)?;
+        Ok(Statement::Class {
+            name,
+            superclass,
+            methods,
+            span: keyword.to(close.span),
+        })
     }
 
-    fn function(&mut self, kind: FunctionKind) -> Result<Statement> {
-        let name = self.get_identifier()?;
+    fn methods(&mut self) -> Result<Vec<Function>> {
+        let mut methods = Vec::new();
+        while self
+            .iter
+            .peek()
+            .is_some_and(|x| x.type_ != Token::RIGHTBRACE)
+        {
+            methods.push(self.function(FunctionKind::Method, None)?);
+        }
+        Ok(methods)
+    }
+
+    /// Functions start at the `fun` keyword, methods at their name
+    fn function(&mut self, kind: FunctionKind, keyword: Option<Span>) -> Result<Function> {
+        let (name, name_span) = self.get_identifier()?;
         self.consume(Token::LEFTPAREN, ParsingError::FnParenOpen(kind))?;
         let mut params = Vec::new();
         if self
@@ -95,11 +241,13 @@ impl Parser {
             .is_some_and(|x| x.type_ != Token::RIGHTPAREN)
         {
             loop {
+                // still a valid function, so report it and keep going
                 if params.len() >= 255 {
-                    println!("Can't have more than 255 parameters.");
-                };
-                params.push(self.get_identifier()?);
-                if self.iter.next_if(|x| x.type_ == Token::COMMA).is_none() {
+                    let error = Self::error(ParsingError::TooManyParams, self.peek_span());
+                    self.errors.push(error);
+                }
+                params.push(self.get_identifier()?.0);
+                if self.next_if(Token::COMMA).is_none() {
                     break;
                 }
             }
@@ -107,92 +255,137 @@ impl Parser {
 
         self.consume(Token::RIGHTPAREN, ParsingError::FnParenClosed(kind))?;
         self.consume(Token::LEFTBRACE, ParsingError::FnNoBraceOpen(kind))?;
-        let body = self.block()?;
-        Ok(Statement::Function(Function { name, params, body }))
+        // a loop around the declaration can't be broken out of from inside the body
+        let enclosing_loops = std::mem::take(&mut self.loop_depth);
+        let body = self.block();
+        self.loop_depth = enclosing_loops;
+        let (body, close) = body?;
+        Ok(Function {
+            name,
+            params,
+            body,
+            span: keyword.unwrap_or(name_span).to(close),
+        })
     }
 
+    /// Errors point at whatever token was there instead
     fn consume(
         &mut self,
         token: impl PartialEq<Token>,
         error: ParsingError,
     ) -> Result<ScannedToken> {
-        self.iter.next_if(|x| token.eq(&x.type_)).ok_or(error)
+        match self.next_if(token) {
+            Some(token) => Ok(token),
+            None => Err(Self::error(error, self.peek_span())),
+        }
     }
 
-    fn var_declaration(&mut self) -> Result<Statement> {
-        let Ok(ScannedToken {
+    fn peek_span(&mut self) -> Span {
+        self.iter.peek().map_or(self.eof, |x| x.span)
+    }
+
+    fn var_declaration(&mut self, keyword: Span) -> Result<Statement> {
+        let Some(ScannedToken {
             type_: Token::IDENTIFIER(name),
             ..
-        }) = self.consume(TokenDiscriminant::IDENTIFIER, ParsingError::NoIdentifier)
+        }) = self.next_if(TokenDiscriminant::IDENTIFIER)
         else {
-            return Err(Self::error(
-                ParsingError::NoIdentifier,
-                self.iter.peek().map(|x| x.line),
-            ));
+            return Err(Self::error(ParsingError::NoIdentifier, self.peek_span()));
         };
 
-        let initializer = if self.iter.next_if(|x| x.type_ == Token::EQUAL).is_some() {
+        let initializer = if self.next_if(Token::EQUAL).is_some() {
             Some(self.expression()?)
         } else {
             None
         };
 
-        self.semicolon()
-            .map(|()| Statement::Var { name, initializer })
+        let semicolon = self.semicolon()?;
+        Ok(Statement::Var {
+            name,
+            initializer,
+            span: keyword.to(semicolon),
+        })
     }
 
-    fn semicolon(&mut self) -> Result<()> {
-        if self.iter.next_if(|x| x.type_ == Token::SEMICOLON).is_some() {
-            Ok(())
-        } else {
-            Err(Self::error(
-                ParsingError::NoSemi,
-                self.iter.peek().map(|x| x.line),
-            ))
-        }
+    fn semicolon(&mut self) -> Result<Span> {
+        self.consume(Token::SEMICOLON, ParsingError::NoSemi)
+            .map(|semicolon| semicolon.span)
     }
 
     fn statement(&mut self) -> Result<Statement> {
-        if self.iter.next_if(|x| x.type_ == Token::PRINT).is_some() {
-            self.print_statement()
-        } else if self.iter.next_if(|x| x.type_ == Token::FOR).is_some() {
-            self.for_statement()
-        } else if self.iter.next_if(|x| x.type_ == Token::WHILE).is_some() {
-            self.while_statement()
-        } else if self.iter.next_if(|x| x.type_ == Token::LEFTBRACE).is_some() {
-            Ok(Statement::Block(self.block()?))
-        } else if self.iter.next_if(|x| x.type_ == Token::IF).is_some() {
-            self.if_statement()
-        } else if let Some(keyword) = self.iter.next_if(|x| x.type_ == Token::RETURN) {
-            self.return_statement(keyword)
+        if let Some(keyword) = self.next_if(Token::PRINT) {
+            self.print_statement(keyword.span)
+        } else if let Some(keyword) = self.next_if(Token::FOR) {
+            self.for_statement(keyword.span)
+        } else if let Some(keyword) = self.next_if(Token::WHILE) {
+            self.while_statement(keyword.span)
+        } else if let Some(open) = self.next_if(Token::LEFTBRACE) {
+            let (statements, close) = self.block()?;
+            Ok(Statement::Block {
+                statements,
+                span: open.span.to(close),
+            })
+        } else if let Some(keyword) = self.next_if(Token::IF) {
+            self.if_statement(keyword.span)
+        } else if let Some(keyword) = self.next_if(Token::RETURN) {
+            self.return_statement(keyword.span)
+        } else if let Some(keyword) = self.next_if(Token::BREAK) {
+            self.loop_jump(keyword, ParsingError::BreakOutsideLoop)
+        } else if let Some(keyword) = self.next_if(Token::CONTINUE) {
+            self.loop_jump(keyword, ParsingError::ContinueOutsideLoop)
         } else {
             self.expression_statement()
         }
     }
 
-    fn return_statement(&mut self, keyword: ScannedToken) -> Result<Statement> {
-        if self.iter.next_if(|x| x.type_ == Token::SEMICOLON).is_some() {
+    fn return_statement(&mut self, keyword: Span) -> Result<Statement> {
+        if let Some(semicolon) = self.next_if(Token::SEMICOLON) {
             Ok(Statement::Return {
-                token: keyword,
                 value: None,
+                span: keyword.to(semicolon.span),
             })
         } else {
             let value = Some(self.expression()?);
-            self.consume(Token::SEMICOLON, ParsingError::NoSemi)?;
+            let semicolon = self.semicolon()?;
             Ok(Statement::Return {
-                token: keyword,
                 value,
+                span: keyword.to(semicolon),
             })
         }
     }
 
-    fn for_statement(&mut self) -> Result<Statement> {
+    fn loop_jump(
+        &mut self,
+        keyword: ScannedToken,
+        outside_loop: ParsingError,
+    ) -> Result<Statement> {
+        let semicolon = self.semicolon()?;
+        // the statement itself is fine, so there's nothing to synchronize past
+        if self.loop_depth == 0 {
+            let error = Self::error(outside_loop, keyword.span);
+            self.errors.push(error);
+        }
+        let span = keyword.span.to(semicolon);
+        Ok(match keyword.type_ {
+            Token::BREAK => Statement::Break { span },
+            _ => Statement::Continue { span },
+        })
+    }
+
+    fn loop_body(&mut self) -> Result<Statement> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn for_statement(&mut self, keyword: Span) -> Result<Statement> {
         self.consume(TokenDiscriminant::LEFTPAREN, ParsingError::ForParenOpen)?;
 
-        let initializer = if self.iter.next_if(|x| x.type_ == Token::SEMICOLON).is_some() {
+        let initializer = if self.next_if(Token::SEMICOLON).is_some() {
             None
-        } else if self.iter.next_if(|x| x.type_ == Token::VAR).is_some() {
-            Some(self.var_declaration()?)
+        } else if let Some(var) = self.next_if(Token::VAR) {
+            Some(self.var_declaration(var.span)?)
         } else {
             Some(self.expression_statement()?)
         };
@@ -220,87 +413,102 @@ impl Parser {
 
         self.consume(TokenDiscriminant::RIGHTPAREN, ParsingError::ForParenClosed)?;
 
-        let body = if let Some(increment) = increment {
-            Statement::Block(vec![self.statement()?, Statement::Expression(increment)])
-        } else {
-            self.statement()?
-        };
+        let body = self.loop_body()?;
+        let span = keyword.to(body.span());
 
-        let condition = condition.unwrap_or(Expression::Literal(LoxObject::Bool(true)));
+        let condition = condition.unwrap_or(Expression::Literal {
+            value: LoxObject::Bool(true),
+            span: keyword,
+        });
 
         let body = Statement::While {
             condition,
             body: Box::new(body),
+            increment,
+            for_loop: true,
+            span,
         };
 
         if let Some(initializer) = initializer {
-            Ok(Statement::Block(vec![initializer, body]))
+            Ok(Statement::Block {
+                statements: vec![initializer, body],
+                span,
+            })
         } else {
             Ok(body)
         }
     }
 
-    fn while_statement(&mut self) -> Result<Statement> {
+    fn while_statement(&mut self, keyword: Span) -> Result<Statement> {
         self.consume(TokenDiscriminant::LEFTPAREN, ParsingError::WhileParenOpen)?;
         let condition = self.expression()?;
         self.consume(
             TokenDiscriminant::RIGHTPAREN,
             ParsingError::WhileParenClosed,
         )?;
-        let body = Box::new(self.statement()?);
-        Ok(Statement::While { condition, body })
+        let body = Box::new(self.loop_body()?);
+        Ok(Statement::While {
+            condition,
+            span: keyword.to(body.span()),
+            body,
+            increment: None,
+            for_loop: false,
+        })
     }
 
-    fn if_statement(&mut self) -> Result<Statement> {
+    fn if_statement(&mut self, keyword: Span) -> Result<Statement> {
         self.consume(TokenDiscriminant::LEFTPAREN, ParsingError::IfParenOpen)?;
         let condition = self.expression()?;
-        eprintln!("if condition: {condition}");
         self.consume(TokenDiscriminant::RIGHTPAREN, ParsingError::IfParenOpen)?;
 
         let then = Box::new(self.statement()?);
 
-        let else_case = if self.iter.next_if(|x| x.type_ == Token::ELSE).is_some() {
+        let else_case = if self.next_if(Token::ELSE).is_some() {
             Some(Box::new(self.statement()?))
         } else {
             None
         };
 
+        let end = else_case.as_ref().unwrap_or(&then).span();
         Ok(Statement::If {
             condition,
             then,
             else_case,
+            span: keyword.to(end),
         })
     }
 
-    fn block(&mut self) -> Result<Vec<Statement>> {
+    /// Parses up to and including the closing brace, handing back its span along with the body
+    fn block(&mut self) -> Result<(Vec<Statement>, Span)> {
         let mut statements = Vec::new();
         while self
             .iter
             .peek()
             .is_some_and(|x| x.type_ != Token::RIGHTBRACE)
         {
-            statements.push(self.declaration()?)
+            statements.extend(self.declaration());
         }
 
-        if self
-            .iter
-            .next_if(|x| x.type_ == Token::RIGHTBRACE)
-            .is_some()
-        {
-            Ok(statements)
-        } else {
-            Err(Self::error(ParsingError::UntermBrace, None))
-        }
+        let close = self.consume(Token::RIGHTBRACE, ParsingError::UntermBrace)?;
+        Ok((statements, close.span))
     }
 
-    fn print_statement(&mut self) -> Result<Statement> {
-        let value = self.expression()?;
-        self.semicolon().map(|()| Statement::Print(value))
+    fn print_statement(&mut self, keyword: Span) -> Result<Statement> {
+        let expression = self.expression()?;
+        let semicolon = self.semicolon()?;
+        Ok(Statement::Print {
+            expression,
+            span: keyword.to(semicolon),
+        })
     }
 
     fn expression_statement(&mut self) -> Result<Statement> {
-        let value = self.expression()?;
-        self.semicolon().map(|()| Statement::Expression(value))
+        let expression = self.expression()?;
+        let semicolon = self.semicolon()?;
+        Ok(Statement::Expression {
+            span: expression.span().to(semicolon),
+            expression,
+        })
     }
 
     fn expression(&mut self) -> Result<Expression> {
@@ -309,13 +517,30 @@ impl Parser {
 
     fn assignment(&mut self) -> Result<Expression> {
         let expression = self.or()?;
-        if let Some(ScannedToken { line, .. }) = self.iter.next_if(|x| x.type_ == Token::EQUAL) {
+        if let Some(equal) = self.next_if(Token::EQUAL) {
             let value = Box::new(self.assignment()?);
-            if let Expression::Variable { name, .. } = expression {
-                return Ok(Expression::Assign { name, value });
-            } else {
-                return Err(Self::error(ParsingError::InvalidAssignment, Some(line)));
-            }
+            let span = expression.span().to(value.span());
+            return match expression {
+                Expression::Variable { name, .. } => Ok(Expression::Assign {
+                    name,
+                    value,
+                    span,
+                    depth: None,
+                }),
+                Expression::Get { object, name, .. } => Ok(Expression::Set {
+                    object,
+                    name,
+                    value,
+                    span,
+                }),
+                Expression::Index { object, index, .. } => Ok(Expression::SetIndex {
+                    object,
+                    index,
+                    value,
+                    span,
+                }),
+                _ => Err(Self::error(ParsingError::InvalidAssignment, equal.span)),
+            };
         }
         Ok(expression)
     }
@@ -369,6 +594,7 @@ impl Parser {
             let right = self.unary()?;
             return Ok(Expression::Unary {
                 operator,
+                span: token.span.to(right.span()),
                 inner: Box::new(right),
             });
         }
@@ -376,21 +602,38 @@ impl Parser {
     }
 
     fn call(&mut self) -> Result<Expression> {
-        let mut expr = self.primary();
-        while self.iter.next_if(|x| x.type_ == Token::LEFTPAREN).is_some() {
-            expr = self.finish_call(expr?);
+        let mut expr = self.primary()?;
+        loop {
+            if self.next_if(Token::LEFTPAREN).is_some() {
+                expr = self.finish_call(expr)?;
+            } else if let Some(dot) = self.next_if(Token::DOT) {
+                let (name, name_span) = self
+                    .get_identifier()
+                    .map_err(|_| Self::error(ParsingError::NoPropertyName, dot.span.after()))?;
+                expr = Expression::Get {
+                    span: expr.span().to(name_span),
+                    object: Box::new(expr),
+                    name: name.into(),
+                };
+            } else if self.next_if(Token::LEFTBRACKET).is_some() {
+                let index = self.expression()?;
+                let close = self.consume(Token::RIGHTBRACKET, ParsingError::UntermIndex)?;
+                expr = Expression::Index {
+                    span: expr.span().to(close.span),
+                    object: Box::new(expr),
+                    index: Box::new(index),
+                };
+            } else {
+                break;
+            }
         }
-        expr
+        Ok(expr)
     }
 
     fn next_if(&mut self, token: impl PartialEq<Token>) -> Option<ScannedToken> {
         self.iter.next_if(|x| token.eq(&x.type_))
     }
 
-    fn next_unless(&mut self, token: impl PartialEq<Token>) -> Option<ScannedToken> {
-        self.iter.next_if(|x| !token.eq(&x.type_))
-    }
-
     fn finish_call(&mut self, callee: Expression) -> Result<Expression> {
         let mut args = Vec::new();
         if self
@@ -400,10 +643,8 @@ impl Parser {
         {
             loop {
                 if args.len() >= 255 {
-                    Self::error(
-                        ParsingError::TooManyArgs,
-                        Some(self.iter.peek().unwrap().line),
-                    );
+                    let error = Self::error(ParsingError::TooManyArgs, self.peek_span());
+                    self.errors.push(error);
                 }
                 args.push(self.expression()?);
                 if self.next_if(Token::COMMA).is_none() {
@@ -413,26 +654,103 @@ impl Parser {
         };
         let paren = self.consume(Token::RIGHTPAREN, ParsingError::FnNoCloseParen)?;
         Ok(Expression::Call {
+            span: callee.span().to(paren.span),
             callee: Box::new(callee),
-            paren,
             args,
         })
     }
 
     fn primary(&mut self) -> Result<Expression> {
-        let Some(ScannedToken { type_: token, line }) = self.iter.next() else {
-            return Err(Self::error(ParsingError::NoExpr, None));
+        // anything else is left where it is so synchronizing can see it
+        let Some(ScannedToken { type_: token, span }) =
+            self.iter.next_if(|x| starts_expression(&x.type_))
+        else {
+            return Err(Self::error(ParsingError::NoExpr, self.peek_span()));
         };
+        let literal = |value| Ok(Expression::Literal { value, span });
         match token {
-            Token::FALSE => Ok(false.into()),
-            Token::TRUE => Ok(true.into()),
-            Token::NIL => Ok(Expression::nil()),
-            Token::NUMBER(num) => Ok(num.into()),
-            Token::STRING(string) => Ok(string.into()),
-            Token::LEFTPAREN => self.handle_paren(),
-            Token::IDENTIFIER(name) => Ok(Expression::Variable { name, line }),
-            _ => Err(Self::error(ParsingError::NoExpr, Some(line))),
+            Token::FALSE => literal(LoxObject::Bool(false)),
+            Token::TRUE => literal(LoxObject::Bool(true)),
+            Token::NIL => literal(LoxObject::Nil),
+            Token::NUMBER(num) => literal(LoxObject::Float(num)),
+            Token::STRING(string) => literal(LoxObject::String(string)),
+            Token::LEFTPAREN => self.handle_paren(span),
+            Token::LEFTBRACKET => self.list(span),
+            // statements starting with a brace are blocks, so this only sees braces in
+            // expression position
+            Token::LEFTBRACE => self.map(span),
+            Token::IDENTIFIER(name) => Ok(Expression::Variable {
+                name,
+                span,
+                depth: None,
+            }),
+            Token::THIS => Ok(Expression::This { span, depth: None }),
+            Token::SUPER => self.super_expression(span),
+            _ => unreachable!("checked by starts_expression"),
+        }
+    }
+
+    fn list(&mut self, open: Span) -> Result<Expression> {
+        let mut elements = Vec::new();
+        if self
+            .iter
+            .peek()
+            .is_some_and(|x| x.type_ != Token::RIGHTBRACKET)
+        {
+            loop {
+                elements.push(self.expression()?);
+                if self.next_if(Token::COMMA).is_none() {
+                    break;
+                }
+            }
         }
+        let close = self.consume(Token::RIGHTBRACKET, ParsingError::UntermList)?;
+        Ok(Expression::List {
+            elements,
+            span: open.to(close.span),
+        })
+    }
+
+    fn map(&mut self, open: Span) -> Result<Expression> {
+        let mut entries = Vec::new();
+        if self
+            .iter
+            .peek()
+            .is_some_and(|x| x.type_ != Token::RIGHTBRACE)
+        {
+            loop {
+                let key = self.expression()?;
+                self.consume(Token::COLON, ParsingError::MapNoColon)?;
+                entries.push((key, self.expression()?));
+                if self.next_if(Token::COMMA).is_none() {
+                    break;
+                }
+            }
+        }
+        let close = self.consume(Token::RIGHTBRACE, ParsingError::UntermMap)?;
+        Ok(Expression::Map {
+            entries,
+            span: open.to(close.span),
+        })
+    }
+
+    fn super_expression(&mut self, keyword: Span) -> Result<Expression> {
+        match self.current_class {
+            ClassKind::Subclass => {}
+            ClassKind::Class => {
+                return Err(Self::error(ParsingError::SuperWithoutSuperclass, keyword))
+            }
+            ClassKind::None => return Err(Self::error(ParsingError::SuperOutsideClass, keyword)),
+        }
+        let dot = self.consume(Token::DOT, ParsingError::SuperNoDot)?;
+        let (method, method_span) = self
+            .get_identifier()
+            .map_err(|_| Self::error(ParsingError::SuperNoMethod, dot.span.after()))?;
+        Ok(Expression::Super {
+            method: method.into(),
+            span: keyword.to(method_span),
+            depth: None,
+        })
     }
 }
 
@@ -448,6 +766,7 @@ impl Parser {
             let operator = LogicalOperator::try_from(token.type_).unwrap();
             let right = Box::new(f(self)?);
             expr = Expression::Logical {
+                span: left.span().to(right.span()),
                 left,
                 operator,
                 right,
@@ -465,11 +784,12 @@ impl Parser {
         // TODO: see if there's a way we can combine the while let to remove the unwrap
 
         while let Some(token) = self.iter.next_if(|x| types.contains(&x.type_)) {
-            let operator = BinaryOperator::from_token(token).unwrap();
+            let operator = BinaryOperator::from_token(&token.type_).unwrap();
             let right = f(self)?;
             let left = Box::new(expr);
             let right = Box::new(right);
             expr = Expression::Binary {
+                span: left.span().to(right.span()),
                 left,
                 operator,
                 right,
@@ -478,32 +798,23 @@ impl Parser {
         Ok(expr)
     }
 
-    fn handle_paren(&mut self) -> Result<Expression> {
+    fn handle_paren(&mut self, open: Span) -> Result<Expression> {
         let expr = self.expression()?;
-        if self
-            .iter
-            .next_if(|x| x.type_ == Token::RIGHTPAREN)
-            .is_some()
-        {
-            Ok(Expression::Grouping(Box::new(expr)))
-        } else {
-            Err(Self::error(
-                ParsingError::UntermParen,
-                self.iter.peek().map(|x| x.line),
-            ))
-        }
+        let close = self.consume(Token::RIGHTPAREN, ParsingError::UntermParen)?;
+        Ok(Expression::Grouping {
+            inner: Box::new(expr),
+            span: open.to(close.span),
+        })
     }
 
-    fn error(error: ParsingError, line: Option<u32>) -> ParsingError {
-        match line {
-            Some(line) => {
-                println!("Error on line {}: {}", line, error)
-            }
-            None => {
-                println!("Error at end of file: {}", error)
-            }
-        };
-        error
+    fn error(error: ParsingError, span: Span) -> ParsingError {
+        match error {
+            located @ ParsingError::At { .. } => located,
+            error => ParsingError::At {
+                error: Box::new(error),
+                span,
+            },
+        }
     }
 
     fn _synchronize(&mut self) {
@@ -534,3 +845,217 @@ impl Parser {
         }
     }
 }
+
+fn starts_expression(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::FALSE
+            | Token::TRUE
+            | Token::NIL
+            | Token::NUMBER(_)
+            | Token::STRING(_)
+            | Token::LEFTPAREN
+            | Token::LEFTBRACKET
+            | Token::LEFTBRACE
+            | Token::IDENTIFIER(_)
+            | Token::THIS
+            | Token::SUPER
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::scan;
+
+    fn parse(source: &str) -> std::result::Result<Vec<Statement>, Vec<ParsingError>> {
+        Parser::new(scan(source).unwrap()).parse()
+    }
+
+    #[test]
+    fn class_declaration() {
+        let statements = parse("class Foo { init(x) { this.x = x; } bar() { return this.x; } }");
+        let Ok([Statement::Class { name, methods, .. }]) = statements.as_deref() else {
+            panic!("expected a single class, got {:?}", statements)
+        };
+        assert_eq!(name.as_ref(), "Foo");
+        assert_eq!(methods.len(), 2);
+    }
+
+    #[test]
+    fn super_outside_subclass() {
+        assert!(parse("class A { f() { return super.f(); } }").is_err());
+        assert!(parse("print super.f;").is_err());
+        assert!(parse("class B < A { f() { return super.f(); } }").is_ok());
+    }
+
+    #[test]
+    fn loop_jumps() {
+        assert!(parse("while (true) { if (true) break; continue; }").is_ok());
+        assert!(parse("break;").is_err());
+        for source in [
+            "for (;;) { fun f() { continue; } }",
+            "while (true) { fun f() { continue; } }",
+        ] {
+            let errors = parse(source).unwrap_err();
+            let codes: Vec<_> = errors.iter().map(ParsingError::code).collect();
+            assert_eq!(codes, ["E0238"], "{source:?}");
+        }
+    }
+
+    #[test]
+    fn desugared_for() {
+        let statements = parse("for (var i = 0; i < 1; i = i + 1) {} while (true) {}").unwrap();
+        let [Statement::Block {
+            statements: desugared,
+            ..
+        }, written] = statements.as_slice()
+        else {
+            panic!("expected a block and a while, got {statements:?}")
+        };
+        assert!(matches!(
+            desugared[1],
+            Statement::While { for_loop: true, .. }
+        ));
+        assert!(matches!(
+            written,
+            Statement::While {
+                for_loop: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn scan_and_parse_errors() {
+        let source = "var x = ;\nprint 1 +;\nvar y = @;\nvar z = #;\nprint 2";
+        let error = super::parse(source).unwrap_err();
+        let found: Vec<_> = error
+            .all()
+            .iter()
+            .map(|e| match e {
+                Error::ScanningError(e) => ("scan", e.span().unwrap().line),
+                Error::ParsingError(e) => ("parse", e.span().unwrap().line),
+                e => panic!("unexpected {e}"),
+            })
+            .collect();
+        // the gaps left by @ and # aren't reported a second time
+        assert_eq!(
+            found,
+            [
+                ("parse", 1),
+                ("parse", 2),
+                ("scan", 3),
+                ("scan", 4),
+                ("parse", 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn error_spans() {
+        // a missing token is reported just past the end of the last one
+        let e = &parse("print 1").unwrap_err()[0];
+        assert!(matches!(e.kind(), ParsingError::NoSemi));
+        assert_eq!(e.span(), Some(Span::new(1, 8, 7, 7)));
+
+        let e = &parse("var a = 1;\n  a + 1 = 2;").unwrap_err()[0];
+        assert!(matches!(e.kind(), ParsingError::InvalidAssignment));
+        assert_eq!(e.span().map(|span| (span.line, span.column)), Some((2, 9)));
+
+        // past the end of a string that ran onto another line
+        let e = &parse("print \"a\nbc\"").unwrap_err()[0];
+        assert!(matches!(e.kind(), ParsingError::NoSemi));
+        assert_eq!(e.span(), Some(Span::new(2, 4, 12, 12)));
+    }
+
+    #[test]
+    fn node_spans() {
+        let source = "if (a) {\n  print a.b[0];\n}";
+        let statements = parse(source).unwrap();
+        assert_eq!(statements[0].span().end, source.len());
+        let Statement::If { then, .. } = &statements[0] else {
+            panic!()
+        };
+        let Statement::Block { statements, .. } = then.as_ref() else {
+            panic!()
+        };
+        let Statement::Print { expression, span } = &statements[0] else {
+            panic!()
+        };
+        assert_eq!(&source[span.start..span.end], "print a.b[0];");
+        assert_eq!(
+            &source[expression.span().start..expression.span().end],
+            "a.b[0]"
+        );
+    }
+
+    #[test]
+    fn every_error() {
+        let errors =
+            parse("print 1 +;\nvar = 2;\n{ print; }\nprint \"fine\";\nfun f( {}").unwrap_err();
+        let lines: Vec<_> = errors
+            .iter()
+            .map(|e| (e.span().unwrap().line, e.kind().to_string()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (1, ParsingError::NoExpr.to_string()),
+                (2, ParsingError::NoIdentifier.to_string()),
+                (3, ParsingError::NoExpr.to_string()),
+                (5, ParsingError::NoIdentifier.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn inherit_self() {
+        assert!(parse("class A < A {}").is_err());
+    }
+
+    #[test]
+    fn property_set() {
+        let statements = parse("a.b.c = 1;");
+        assert!(matches!(
+            statements.as_deref(),
+            Ok([Statement::Expression {
+                expression: Expression::Set { .. },
+                ..
+            }])
+        ));
+    }
+
+    #[test]
+    fn index_set() {
+        let statements = parse("xs[0][1] = [1, 2];");
+        assert!(matches!(
+            statements.as_deref(),
+            Ok([Statement::Expression {
+                expression: Expression::SetIndex { .. },
+                ..
+            }])
+        ));
+        assert!(parse("xs[0;").is_err());
+    }
+
+    #[test]
+    fn map_literal_or_block() {
+        let statements = parse("{ print {\"a\": 1}; }");
+        let Ok([Statement::Block { statements, .. }]) = statements.as_deref() else {
+            panic!("expected a block, got {:?}", statements)
+        };
+        assert!(matches!(
+            statements.as_slice(),
+            [Statement::Print {
+                expression: Expression::Map { .. },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn invalid_assignment() {
+        assert!(parse("a.b() = 1;").is_err());
+    }
+}
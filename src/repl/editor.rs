@@ -0,0 +1,456 @@
+use super::terminal::Terminal;
+use std::fs::{self, OpenOptions};
+use std::io::{self, stdin, stdout, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// anything older than this is dropped when the history file is loaded
+const HISTORY_MAX: usize = 1000;
+
+// how long to wait for the rest of an escape sequence before deciding ESC was pressed on its own
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Reads lines with arrow key editing and history when stdin is a terminal, and falls back to
+/// plain buffered reads when it's a pipe
+pub struct Editor {
+    history: Vec<String>,
+    file: Option<PathBuf>,
+    terminal: Option<Terminal>,
+}
+
+/// What the person at the prompt did
+#[derive(Debug, PartialEq, Eq)]
+pub enum Input {
+    Line(String),
+    /// Ctrl-C, whatever was typed so far should be thrown away
+    Interrupt,
+    /// Ctrl-D on an empty line, or the pipe ran dry
+    Eof,
+}
+
+/// Where keys come from while a line is being edited
+pub trait Tty {
+    /// The next byte typed, or None if the input ended or nothing came before the timeout
+    fn byte(&mut self, timeout: Option<Duration>) -> io::Result<Option<u8>>;
+    /// How many columns there are before the line wraps
+    fn width(&self) -> usize;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Enter,
+    /// Ctrl-C
+    Interrupt,
+    /// Ctrl-D, which deletes unless there's nothing left to delete
+    EndOfInput,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// Ctrl-U
+    Clear,
+    /// Anything else, including ESC on its own
+    Ignored,
+}
+
+impl Editor {
+    /// Loads history from `~/.lox_history`, a missing or unreadable file just means no history
+    pub fn new() -> Self {
+        let file = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".lox_history"));
+        Self {
+            terminal: stdin().is_terminal().then(Terminal::open).flatten(),
+            ..Self::with_history(file)
+        }
+    }
+
+    // a file that's grown past the limit is cut back down, so it can't grow forever
+    fn with_history(file: Option<PathBuf>) -> Self {
+        let mut history: Vec<String> = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|contents| contents.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        let old = history.len().saturating_sub(HISTORY_MAX);
+        if old > 0 {
+            history.drain(..old);
+            if let Some(file) = &file {
+                let _ = fs::write(file, history.join("\n") + "\n");
+            }
+        }
+        Self {
+            history,
+            file,
+            terminal: None,
+        }
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+        print!("{prompt}");
+        stdout().flush()?;
+        let Some(mut terminal) = self.terminal.take() else {
+            // piped in lines are a script rather than anything typed, so they stay out of history
+            return read_plain();
+        };
+        let edited = terminal
+            .raw()
+            .and_then(|_raw| self.edit(&mut terminal, &mut stdout().lock(), prompt));
+        self.terminal = Some(terminal);
+        let input = edited?;
+        if let Input::Line(line) = &input {
+            self.remember(line);
+        }
+        Ok(input)
+    }
+
+    // history is appended to as we go so it survives the process getting killed
+    fn remember(&mut self, line: &str) {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if let Some(file) = &self.file {
+            let appended = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+                .and_then(|mut file| writeln!(file, "{line}"));
+            // losing history isn't worth interrupting anyone over
+            if appended.is_err() {
+                self.file = None;
+            }
+        }
+    }
+
+    // the prompt has already been written, the line is redrawn after it on every key
+    fn edit(&self, tty: &mut impl Tty, out: &mut impl Write, prompt: &str) -> io::Result<Input> {
+        let mut line = Line::default();
+        // the row the cursor was left on, counting from the prompt's
+        let mut row = 0;
+        // where we are in history, history.len() being the line being typed
+        let mut recalled = self.history.len();
+        let mut typed = String::new();
+        loop {
+            let Some(key) = read_key(tty)? else {
+                return Ok(Input::Eof);
+            };
+            let done = match key {
+                Key::Enter => Some((Input::Line(line.text() + "\n"), "")),
+                Key::Interrupt => Some((Input::Interrupt, "^C")),
+                Key::EndOfInput if line.chars.is_empty() => Some((Input::Eof, "")),
+                _ => None,
+            };
+            if let Some((input, shown)) = done {
+                // the cursor might be partway through a wrapped line, the next one goes below it
+                line.cursor = line.chars.len();
+                line.redraw(out, prompt, tty.width(), row)?;
+                write!(out, "{shown}\r\n")?;
+                out.flush()?;
+                return Ok(input);
+            }
+            match key {
+                Key::Char(c) => line.insert(c),
+                Key::Backspace => line.backspace(),
+                Key::Delete | Key::EndOfInput => line.delete(),
+                Key::Left => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right => line.cursor = (line.cursor + 1).min(line.chars.len()),
+                Key::Home => line.cursor = 0,
+                Key::End => line.cursor = line.chars.len(),
+                Key::Clear => line = Line::default(),
+                Key::Up if recalled > 0 => {
+                    if recalled == self.history.len() {
+                        typed = line.text();
+                    }
+                    recalled -= 1;
+                    line = Line::from(self.history[recalled].as_str());
+                }
+                Key::Down if recalled < self.history.len() => {
+                    recalled += 1;
+                    line = Line::from(self.history.get(recalled).unwrap_or(&typed).as_str());
+                }
+                _ => {}
+            }
+            row = line.redraw(out, prompt, tty.width(), row)?;
+        }
+    }
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_plain() -> io::Result<Input> {
+    let mut line = String::new();
+    if stdin().lock().read_line(&mut line)? == 0 {
+        Ok(Input::Eof)
+    } else {
+        Ok(Input::Line(line))
+    }
+}
+
+/// None once the input has run out
+fn read_key(tty: &mut impl Tty) -> io::Result<Option<Key>> {
+    let Some(byte) = tty.byte(None)? else {
+        return Ok(None);
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        0x03 => Key::Interrupt,
+        0x04 => Key::EndOfInput,
+        // Ctrl-A and Ctrl-E
+        0x01 => Key::Home,
+        0x05 => Key::End,
+        0x15 => Key::Clear,
+        0x7f | 0x08 => Key::Backspace,
+        0x1b => escape(tty)?,
+        byte if byte < 0x20 => Key::Ignored,
+        byte => {
+            // the rest of a multi-byte character follows straight after its first byte
+            let len = match byte {
+                0xf0.. => 4,
+                0xe0.. => 3,
+                0xc0.. => 2,
+                _ => 1,
+            };
+            let mut encoded = vec![byte];
+            for _ in 1..len {
+                encoded.extend(tty.byte(None)?);
+            }
+            match std::str::from_utf8(&encoded).map(|text| text.chars().next()) {
+                Ok(Some(c)) => Key::Char(c),
+                _ => Key::Ignored,
+            }
+        }
+    };
+    Ok(Some(key))
+}
+
+// the sequences keys send are written all at once, so a gap after the ESC means it was pressed
+// by itself. Sequences that aren't understood are still read to the end so none of them ends up
+// typed into the line
+fn escape(tty: &mut impl Tty) -> io::Result<Key> {
+    let Some(kind @ (b'[' | b'O')) = tty.byte(Some(ESCAPE_TIMEOUT))? else {
+        return Ok(Key::Ignored);
+    };
+    let mut parameters = Vec::new();
+    let last = loop {
+        match tty.byte(Some(ESCAPE_TIMEOUT))? {
+            Some(byte @ 0x40..=0x7e) => break byte,
+            Some(byte) if kind == b'[' => parameters.push(byte),
+            _ => return Ok(Key::Ignored),
+        }
+    };
+    Ok(match (parameters.as_slice(), last) {
+        ([], b'A') => Key::Up,
+        ([], b'B') => Key::Down,
+        ([], b'C') => Key::Right,
+        ([], b'D') => Key::Left,
+        ([], b'H') | (b"1" | b"7", b'~') => Key::Home,
+        ([], b'F') | (b"4" | b"8", b'~') => Key::End,
+        (b"3", b'~') => Key::Delete,
+        _ => Key::Ignored,
+    })
+}
+
+/// The line being edited, kept as chars so the cursor can't land inside one
+#[derive(Default)]
+struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl From<&str> for Line {
+    fn from(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        Self {
+            cursor: chars.len(),
+            chars,
+        }
+    }
+}
+
+impl Line {
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    // the line can wrap over several rows, so this goes back up to the prompt's row, writes it
+    // all out again and clears whatever was left below, then walks the cursor back to where it
+    // belongs. Returns the row the cursor ends up on
+    fn redraw(
+        &self,
+        out: &mut impl Write,
+        prompt: &str,
+        width: usize,
+        row: usize,
+    ) -> io::Result<usize> {
+        let width = width.max(1);
+        let start = prompt.chars().count();
+        let (end, cursor) = (start + self.chars.len(), start + self.cursor);
+        if row > 0 {
+            write!(out, "\x1b[{row}A")?;
+        }
+        write!(out, "\r{prompt}{}", self.text())?;
+        // a full row leaves the cursor hanging off the end until something else is written,
+        // so it's moved down by hand to be somewhere definite
+        if end > 0 && end % width == 0 {
+            write!(out, "\r\n")?;
+        }
+        write!(out, "\x1b[J")?;
+        let up = end / width - cursor / width;
+        if up > 0 {
+            write!(out, "\x1b[{up}A")?;
+        }
+        write!(out, "\r")?;
+        let column = cursor % width;
+        if column > 0 {
+            write!(out, "\x1b[{column}C")?;
+        }
+        out.flush()?;
+        Ok(cursor / width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    // None stands for a pause, like the one after ESC pressed on its own
+    struct Keys(VecDeque<Option<u8>>);
+
+    impl Tty for Keys {
+        fn byte(&mut self, timeout: Option<Duration>) -> io::Result<Option<u8>> {
+            match self.0.pop_front() {
+                Some(None) if timeout.is_none() => self.byte(timeout),
+                Some(byte) => Ok(byte),
+                None => Ok(None),
+            }
+        }
+
+        fn width(&self) -> usize {
+            10
+        }
+    }
+
+    fn keys(typed: &[&[u8]]) -> Keys {
+        let mut keys = VecDeque::new();
+        for bytes in typed {
+            keys.extend(bytes.iter().copied().map(Some));
+            keys.push_back(None);
+        }
+        Keys(keys)
+    }
+
+    fn edit(editor: &Editor, typed: &[&[u8]]) -> (Input, String) {
+        let mut out = Vec::new();
+        let input = editor.edit(&mut keys(typed), &mut out, "> ").unwrap();
+        (input, String::from_utf8(out).unwrap())
+    }
+
+    fn line(text: &str) -> Input {
+        Input::Line(format!("{text}\n"))
+    }
+
+    #[test]
+    fn key_handling() {
+        let mut editor = Editor::with_history(None);
+        editor.history = vec!["print 1;".into(), "print 2;".into()];
+        for (typed, expected) in [
+            (
+                &[b"prnt" as &[u8], b"\x1b[D\x1b[D", b"i\r"][..],
+                line("print"),
+            ),
+            (&[b"ab\x01", b"\x1b[3~", b"\x05c\x7f\r"], line("b")),
+            (&[b"x\x1b[A", b"\x1b[A", b"\x1b[A", b"\r"], line("print 1;")),
+            (&[b"x\x1b[A", b"\x1b[B", b"\r"], line("x")),
+            // a lone ESC, then keys that have nothing to do with it
+            (&[b"\x1b", b"[A\r"], line("[A")),
+            (&[b"a\x1b[5~", b"\x1bOH", b"b\r"], line("ba")),
+            (&[b"caf\xc3\xa9\r"], line("caf\u{e9}")),
+            (&[b"print\x15", b"x\x04\x03"], Input::Interrupt),
+            (&[b"a\x01\x04", b"\x04"], Input::Eof),
+            (&[b"print"], Input::Eof),
+        ] {
+            assert_eq!(edit(&editor, typed).0, expected, "{typed:?}");
+        }
+    }
+
+    #[test]
+    fn wrapped_redraw() {
+        let editor = Editor::with_history(None);
+        // "> " and twelve characters wrap onto a second row of the ten columns
+        let (_, out) = edit(&editor, &[b"abcdefghijkl", b"\x01", b"\r"]);
+        let home = out
+            .rfind("\x1b[1A\r> abcdefghijkl")
+            .expect("goes back up a row first");
+        assert!(out[home..].contains("\x1b[1A\r\x1b[2C"), "{out:?}");
+        // exactly filling the row moves on to the next before clearing
+        let (_, out) = edit(&editor, &[b"abcdefgh\r"]);
+        assert!(out.ends_with("> abcdefgh\r\n\x1b[J\r\r\n"), "{out:?}");
+    }
+
+    #[test]
+    fn history_file() {
+        let file = std::env::temp_dir().join(format!("lox_history_{}", std::process::id()));
+        let old: Vec<_> = (0..HISTORY_MAX + 5)
+            .map(|i| format!("print {i};"))
+            .collect();
+        fs::write(&file, old.join("\n") + "\n").unwrap();
+
+        let mut editor = Editor::with_history(Some(file.clone()));
+        assert_eq!(editor.history.len(), HISTORY_MAX);
+        assert_eq!(editor.history[0], "print 5;");
+        for line in ["print 1004;\n", " \n", "var a;\n", "var a;\n"] {
+            editor.remember(line);
+        }
+
+        // only the one line was new, so the oldest goes to make room for it
+        let reloaded = Editor::with_history(Some(file.clone()));
+        let saved = fs::read_to_string(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+        assert_eq!(reloaded.history.len(), HISTORY_MAX);
+        assert_eq!(reloaded.history[0], "print 6;");
+        assert_eq!(reloaded.history.last().unwrap(), "var a;");
+        assert_eq!(saved.lines().count(), HISTORY_MAX);
+    }
+
+    #[test]
+    fn line_editing() {
+        let mut line = Line::from("prnt 1");
+        line.cursor = 2;
+        line.insert('i');
+        line.cursor = line.chars.len();
+        line.backspace();
+        line.insert('ü');
+        line.cursor = 0;
+        line.delete();
+        assert_eq!(line.text(), "rint ü");
+        assert_eq!(line.cursor, 0);
+    }
+}
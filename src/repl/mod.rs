@@ -0,0 +1,239 @@
+use crate::diagnostics::{ErrorFormat, Reporter};
+use crate::error::Error;
+use crate::parser::Parser;
+use crate::scanner::{self, ScanningError};
+use crate::syntax_trees::export::AstFormat;
+use crate::syntax_trees::expression::Expression;
+use crate::syntax_trees::lox_object::LoxObject;
+use crate::token::Token;
+use crate::{dump_ast, dump_tokens, read_source, Backend, Session};
+
+mod editor;
+mod terminal;
+
+use editor::{Editor, Input};
+
+pub fn run_prompt(backend: Backend, format: ErrorFormat) -> Result<(), Error> {
+    let mut editor = Editor::new();
+    let mut repl = Repl {
+        session: Session::new(backend),
+        backend,
+        reporter: Reporter::new(format, None),
+    };
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() { "> " } else { "... " };
+        let line = match editor.read_line(prompt)? {
+            Input::Line(line) => line,
+            Input::Interrupt => {
+                input.clear();
+                continue;
+            }
+            Input::Eof => return Ok(()),
+        };
+        // commands only count at the start of an input, not halfway through a statement
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            match Command::parse(&line) {
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => repl.command(command),
+                Err(message) => eprintln!("{message}"),
+            }
+            continue;
+        }
+        // a blank line gives up on waiting, so a mistake doesn't leave the prompt stuck
+        let blank = line.trim().is_empty();
+        input.push_str(&line);
+        if let Some(expression) = bare_expression(&input) {
+            // like Python, nil isn't worth echoing
+            match repl.session.evaluate(expression, &input, &repl.reporter) {
+                Ok(LoxObject::Nil) | Err(_) => {}
+                Ok(value) => println!("{value}"),
+            }
+            input.clear();
+            continue;
+        }
+        if input.trim().is_empty() || (!blank && is_incomplete(&input)) {
+            if blank {
+                input.clear();
+            }
+            continue;
+        }
+        // the session already reported the error, the prompt just carries on
+        let _ = repl.session.run(&input, &repl.reporter);
+        input.clear();
+    }
+}
+
+const HELP: &str = "\
+:env           list the globals that have been defined
+:reset         forget every global
+:load <file>   run a file in this session
+:tokens <code> show what the scanner makes of some code
+:ast <code>    show what the parser makes of some code
+:quit          leave, Ctrl-D does the same";
+
+/// The `:` commands the REPL understands on top of Lox itself
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Env,
+    Reset,
+    Load(String),
+    Tokens(String),
+    Ast(String),
+    Help,
+    Quit,
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let argument = argument.trim().to_string();
+        let command = match name {
+            ":env" => Self::Env,
+            ":reset" => Self::Reset,
+            ":load" => Self::Load(argument.clone()),
+            ":tokens" => Self::Tokens(argument.clone()),
+            ":ast" => Self::Ast(argument.clone()),
+            ":help" => Self::Help,
+            ":quit" | ":q" => Self::Quit,
+            _ => return Err(format!("Unknown command {name}, try :help")),
+        };
+        if argument.is_empty() && matches!(command, Self::Load(_)) {
+            return Err(":load needs a file to run".to_string());
+        }
+        Ok(command)
+    }
+}
+
+struct Repl {
+    session: Session,
+    backend: Backend,
+    reporter: Reporter,
+}
+
+impl Repl {
+    fn command(&mut self, command: Command) {
+        match command {
+            Command::Env => {
+                // natives are always there, so they'd just be noise
+                for (name, value) in self.session.globals() {
+                    if !matches!(value, LoxObject::Native(_)) {
+                        println!("{name} = {value}");
+                    }
+                }
+            }
+            Command::Reset => self.session = Session::new(self.backend),
+            Command::Load(path) => {
+                let reporter = Reporter::new(self.reporter.format, Some(&path));
+                match read_source(&path) {
+                    Ok(source) => {
+                        let _ = self.session.run(&source, &reporter);
+                    }
+                    Err(e) => reporter.report(&e, ""),
+                }
+            }
+            Command::Tokens(source) => match dump_tokens(&source) {
+                Ok(tokens) => print!("{tokens}"),
+                Err(e) => self.reporter.report(&e, &source),
+            },
+            Command::Ast(source) => match dump_ast(&source, AstFormat::default()) {
+                Ok(statements) => print!("{statements}"),
+                Err(e) => self.reporter.report(&e, &source),
+            },
+            Command::Help => println!("{HELP}"),
+            Command::Quit => {}
+        }
+    }
+}
+
+/// The expression if that's all the input is, with or without a semicolon after it. Input
+/// starting with a brace is a block whenever it can be, so `{}` does nothing rather than echo
+/// an empty map, while a map that couldn't be a block still gets shown
+fn bare_expression(source: &str) -> Option<Expression> {
+    let tokens = scanner::scan(source).ok()?;
+    let braced = tokens.first().is_some_and(|x| x.type_ == Token::LEFTBRACE);
+    if braced && Parser::new(tokens.clone()).parse().is_ok() {
+        return None;
+    }
+    Parser::new(tokens).parse_expression().ok()
+}
+
+/// Whether the input only failed because it stopped early, like an open brace or string or a
+/// missing final semicolon, rather than because of a real mistake. Only the first error counts,
+/// anything after it could just be fallout
+fn is_incomplete(source: &str) -> bool {
+    let tokens = match scanner::scan(source) {
+        Ok(tokens) => tokens,
+        // strings are the only thing the scanner sees running off the end
+        Err(errors) => return errors[0].kind() == &ScanningError::UntermString,
+    };
+    let end = tokens.last().map_or(0, |token| token.span.end);
+    match Parser::new(tokens).parse() {
+        Ok(_) => false,
+        Err(errors) => errors[0].span().is_some_and(|span| span.start >= end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        assert_eq!(Command::parse(":env\n"), Ok(Command::Env));
+        assert_eq!(
+            Command::parse(":load  dir/a b.lox \n"),
+            Ok(Command::Load("dir/a b.lox".into()))
+        );
+        assert_eq!(
+            Command::parse(":tokens print 1;"),
+            Ok(Command::Tokens("print 1;".into()))
+        );
+        assert!(Command::parse(":load").is_err());
+        assert!(Command::parse(":nope").is_err());
+    }
+
+    #[test]
+    fn bare_expressions() {
+        for source in ["1 + 2", "x;", "f(1)\n", "a.b = [1, 2];", "{\"k\": 1}"] {
+            assert!(bare_expression(source).is_some(), "{source:?}");
+        }
+        // `{}` is an empty block rather than an empty map
+        for source in [
+            "print 1;",
+            "x; y;",
+            "1 +",
+            "var a = 1",
+            "{}",
+            "{}\n",
+            "{ x; }",
+        ] {
+            assert!(bare_expression(source).is_none(), "{source:?}");
+        }
+    }
+
+    #[test]
+    fn incomplete_input() {
+        for source in [
+            "fun f() {",
+            "fun f() {\n  print 1;\n",
+            "print (1 +",
+            "var s = \"multi\nline",
+            "print 1 // comment\n",
+            "if (x)",
+        ] {
+            assert!(is_incomplete(source), "{source:?} should want more");
+        }
+        for source in [
+            "",
+            "print 1;",
+            "print 1 +;",
+            "print @",
+            "fun f() { } }",
+            "var 1",
+        ] {
+            assert!(!is_incomplete(source), "{source:?} should be finished");
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use super::editor::Tty;
+use std::io;
+use std::time::Duration;
+
+/// Stdin when it's a terminal. Its settings are read once up front so raw mode can be switched
+/// on for each line being edited and back off again while the code it turned into runs
+#[cfg(unix)]
+pub struct Terminal {
+    saved: libc::termios,
+}
+
+/// Puts the saved settings back when dropped
+#[cfg(unix)]
+pub struct RawMode {
+    saved: libc::termios,
+}
+
+#[cfg(unix)]
+impl Terminal {
+    /// None when stdin isn't a terminal, so there's nothing to edit with
+    pub fn open() -> Option<Self> {
+        let mut saved = std::mem::MaybeUninit::uninit();
+        // SAFETY: tcgetattr fills in the whole termios when it succeeds
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, saved.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        Some(Self {
+            saved: unsafe { saved.assume_init() },
+        })
+    }
+
+    /// Keys arrive one at a time without being echoed, and nothing gets translated on the way
+    /// out either, so newlines need a `\r` of their own until this is dropped
+    pub fn raw(&self) -> io::Result<RawMode> {
+        let mut raw = self.saved;
+        // SAFETY: only changes the copy it's given
+        unsafe { libc::cfmakeraw(&mut raw) };
+        set(&raw)?;
+        Ok(RawMode { saved: self.saved })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = set(&self.saved);
+    }
+}
+
+#[cfg(unix)]
+fn set(termios: &libc::termios) -> io::Result<()> {
+    // SAFETY: the termios is a valid one that came from tcgetattr
+    if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, termios) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// reads go straight to the file descriptor, anything sat in a buffer would be invisible to poll
+#[cfg(unix)]
+impl Tty for Terminal {
+    fn byte(&mut self, timeout: Option<Duration>) -> io::Result<Option<u8>> {
+        loop {
+            if let Some(timeout) = timeout {
+                let mut stdin = libc::pollfd {
+                    fd: libc::STDIN_FILENO,
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                let millis = timeout.as_millis().try_into().unwrap_or(libc::c_int::MAX);
+                // SAFETY: one pollfd, and it's the one passed in
+                match unsafe { libc::poll(&mut stdin, 1, millis) } {
+                    0 => return Ok(None),
+                    -1 => match io::Error::last_os_error() {
+                        e if e.kind() == io::ErrorKind::Interrupted => continue,
+                        e => return Err(e),
+                    },
+                    _ => {}
+                }
+            }
+            let mut byte = 0u8;
+            // SAFETY: reads at most the one byte there's room for
+            match unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) } {
+                0 => return Ok(None),
+                -1 => match io::Error::last_os_error() {
+                    // a resize sends SIGWINCH, which isn't a reason to stop reading
+                    e if e.kind() == io::ErrorKind::Interrupted => continue,
+                    e => return Err(e),
+                },
+                _ => return Ok(Some(byte)),
+            }
+        }
+    }
+
+    fn width(&self) -> usize {
+        // SAFETY: winsize is plain data, and TIOCGWINSZ only writes into the one it's given
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let found = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+        match size.ws_col {
+            columns if found && columns > 0 => columns.into(),
+            _ => 80,
+        }
+    }
+}
+
+/// There's no termios to put into raw mode elsewhere, so input is always read a line at a time
+#[cfg(not(unix))]
+pub struct Terminal;
+
+#[cfg(not(unix))]
+pub struct RawMode;
+
+#[cfg(not(unix))]
+impl Terminal {
+    pub fn open() -> Option<Self> {
+        None
+    }
+
+    pub fn raw(&self) -> io::Result<RawMode> {
+        Ok(RawMode)
+    }
+}
+
+#[cfg(not(unix))]
+impl Tty for Terminal {
+    fn byte(&mut self, _: Option<Duration>) -> io::Result<Option<u8>> {
+        unreachable!("never opened")
+    }
+
+    fn width(&self) -> usize {
+        unreachable!("never opened")
+    }
+}
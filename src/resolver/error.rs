@@ -0,0 +1,53 @@
+use crate::scanner::Span;
+use crate::token::SmartString;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum ResolvingError {
+    #[error("Can't read local variable '{0}' in its own initializer.")]
+    OwnInitializer(SmartString),
+    #[error("Already a variable named '{0}' in this scope.")]
+    AlreadyDeclared(SmartString),
+    #[error("Can't return from top-level code.")]
+    TopLevelReturn,
+    #[error("Can't return a value from an initializer.")]
+    ReturnFromInit,
+    #[error("Can't use 'this' outside of a class.")]
+    ThisOutsideClass,
+
+    /// Any of the above, along with where it happened
+    #[error("{error}")]
+    At {
+        error: Box<ResolvingError>,
+        span: Span,
+    },
+}
+
+impl ResolvingError {
+    /// Resolving errors are numbered E03xx
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::At { error, .. } => error.code(),
+            Self::OwnInitializer(_) => "E0301",
+            Self::AlreadyDeclared(_) => "E0302",
+            Self::TopLevelReturn => "E0303",
+            Self::ReturnFromInit => "E0304",
+            Self::ThisOutsideClass => "E0305",
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::At { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// What went wrong, without the location
+    pub fn kind(&self) -> &Self {
+        match self {
+            Self::At { error, .. } => error.kind(),
+            error => error,
+        }
+    }
+}
@@ -0,0 +1,377 @@
+pub mod error;
+pub use error::ResolvingError;
+
+use crate::scanner::Span;
+use crate::syntax_trees::expression::Expression;
+use crate::syntax_trees::statement::{Function, Statement};
+use crate::token::SmartString;
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, Vec<ResolvingError>>;
+
+/// Works out how many scopes up every local variable lives before anything runs, writing the
+/// depth into the tree so the interpreter can skip searching by name. Anything left unresolved
+/// is assumed to be a global
+pub fn resolve(statements: &mut [Statement]) -> Result<()> {
+    let mut resolver = Resolver::default();
+    resolver.resolve_statements(statements);
+    if resolver.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(resolver.errors)
+    }
+}
+
+/// Resolves an expression on its own as if it were a top level statement
+pub fn resolve_expression(expression: &mut Expression) -> Result<()> {
+    let mut resolver = Resolver::default();
+    resolver.expression(expression);
+    if resolver.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(resolver.errors)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ClassType {
+    None,
+    Class,
+}
+
+struct Resolver {
+    // the bool is whether the variable's initializer has finished
+    scopes: Vec<HashMap<SmartString, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<ResolvingError>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self {
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl Resolver {
+    fn resolve_statements(&mut self, statements: &mut [Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Block { statements, .. } => {
+                self.begin_scope();
+                self.resolve_statements(statements);
+                self.end_scope();
+            }
+            Statement::Var {
+                name,
+                initializer,
+                span,
+            } => {
+                self.declare(name, *span);
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.define(name);
+            }
+            Statement::Function(function) => {
+                let name = SmartString::from(function.name.clone());
+                self.declare(&name, function.span);
+                self.define(&name);
+                self.function(function, FunctionType::Function);
+            }
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+                span,
+            } => {
+                let enclosing = self.current_class;
+                self.current_class = ClassType::Class;
+                let name = SmartString::from(name.clone());
+                self.declare(&name, *span);
+                self.define(&name);
+
+                if let Some(superclass) = superclass {
+                    self.expression(superclass);
+                }
+
+                // mirrors the scope bound methods get called in
+                self.begin_scope();
+                self.define("this");
+                if superclass.is_some() {
+                    self.define("super");
+                }
+                for method in methods {
+                    let kind = if method.name.as_ref() == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+                    self.function(method, kind);
+                }
+                self.end_scope();
+
+                self.current_class = enclosing;
+            }
+            Statement::Expression { expression, .. } | Statement::Print { expression, .. } => {
+                self.expression(expression)
+            }
+            Statement::If {
+                condition,
+                then,
+                else_case,
+                ..
+            } => {
+                self.expression(condition);
+                self.statement(then);
+                if let Some(else_case) = else_case {
+                    self.statement(else_case);
+                }
+            }
+            Statement::Return { value, span } => {
+                if self.current_function == FunctionType::None {
+                    self.error(ResolvingError::TopLevelReturn, *span);
+                }
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(ResolvingError::ReturnFromInit, *span);
+                    }
+                    self.expression(value);
+                }
+            }
+            Statement::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => {
+                self.expression(condition);
+                self.statement(body);
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+            }
+            Statement::Break { .. } | Statement::Continue { .. } => {}
+        }
+    }
+
+    fn expression(&mut self, expression: &mut Expression) {
+        match expression {
+            Expression::Variable { name, span, depth } => {
+                if self
+                    .scopes
+                    .last()
+                    .is_some_and(|scope| scope.get(name) == Some(&false))
+                {
+                    self.error(ResolvingError::OwnInitializer(name.clone()), *span);
+                }
+                *depth = self.resolve_local(name);
+            }
+            Expression::Assign {
+                name, value, depth, ..
+            } => {
+                self.expression(value);
+                *depth = self.resolve_local(name);
+            }
+            Expression::This { span, depth } => {
+                if self.current_class == ClassType::None {
+                    self.error(ResolvingError::ThisOutsideClass, *span);
+                }
+                *depth = self.resolve_local("this");
+            }
+            Expression::Super { depth, .. } => *depth = self.resolve_local("super"),
+            Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Call { callee, args, .. } => {
+                self.expression(callee);
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            Expression::Get { object, .. } => self.expression(object),
+            Expression::Set { object, value, .. } => {
+                self.expression(value);
+                self.expression(object);
+            }
+            Expression::Grouping { inner, .. } | Expression::Unary { inner, .. } => {
+                self.expression(inner)
+            }
+            Expression::Literal { .. } => {}
+            Expression::List { elements, .. } => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            Expression::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            Expression::Index { object, index, .. } => {
+                self.expression(object);
+                self.expression(index);
+            }
+            Expression::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.expression(object);
+                self.expression(index);
+                self.expression(value);
+            }
+        }
+    }
+
+    fn function(&mut self, function: &mut Function, kind: FunctionType) {
+        let enclosing = self.current_function;
+        self.current_function = kind;
+
+        self.begin_scope();
+        for param in &function.params {
+            let param = SmartString::from(param.as_ref());
+            self.declare(&param, function.span);
+            self.define(&param);
+        }
+        self.resolve_statements(&mut function.body);
+        self.end_scope();
+
+        self.current_function = enclosing;
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name))
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &SmartString, span: Span) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if scope.insert(name.clone(), false).is_some() {
+            self.error(ResolvingError::AlreadyDeclared(name.clone()), span);
+        }
+    }
+
+    fn define(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.into(), true);
+        }
+    }
+
+    fn error(&mut self, error: ResolvingError, span: Span) {
+        self.errors.push(ResolvingError::At {
+            error: Box::new(error),
+            span,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::scan;
+
+    fn resolved(source: &str) -> Result<Vec<Statement>> {
+        let mut statements = Parser::new(scan(source).unwrap()).parse().unwrap();
+        resolve(&mut statements)?;
+        Ok(statements)
+    }
+
+    #[test]
+    fn local_depth() {
+        let statements = resolved("{ var a = 1; { print a; } }").unwrap();
+        let Statement::Block {
+            statements: outer, ..
+        } = &statements[0]
+        else {
+            panic!()
+        };
+        let Statement::Block {
+            statements: inner, ..
+        } = &outer[1]
+        else {
+            panic!()
+        };
+        assert!(matches!(
+            inner[0],
+            Statement::Print {
+                expression: Expression::Variable { depth: Some(1), .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn globals_stay_unresolved() {
+        let statements = resolved("var a = 1; print a;").unwrap();
+        assert!(matches!(
+            statements[1],
+            Statement::Print {
+                expression: Expression::Variable { depth: None, .. },
+                ..
+            }
+        ));
+    }
+
+    fn error(source: &str) -> ResolvingError {
+        resolved(source).unwrap_err().remove(0)
+    }
+
+    #[test]
+    fn own_initializer() {
+        let e = error("{ var a = a; }");
+        assert!(matches!(e.kind(), ResolvingError::OwnInitializer(_)));
+        // points at the a being read, not the one being declared
+        assert_eq!(e.span().map(|span| span.column), Some(11));
+    }
+
+    #[test]
+    fn duplicate_declaration() {
+        assert!(matches!(
+            error("fun f() { var a = 1; var a = 2; }").kind(),
+            ResolvingError::AlreadyDeclared(_)
+        ));
+    }
+
+    #[test]
+    fn top_level_return() {
+        assert!(matches!(
+            error("return 1;").kind(),
+            ResolvingError::TopLevelReturn
+        ));
+    }
+}
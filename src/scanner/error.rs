@@ -1,3 +1,4 @@
+use super::Span;
 use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq)]
@@ -8,15 +9,49 @@ pub enum ScanningError {
     Syntax,
     #[error("Float Parsing Error")]
     FloatParse(#[from] std::num::ParseFloatError),
+
+    /// Any of the above, along with where it happened
+    #[error("{error}")]
+    At {
+        error: Box<ScanningError>,
+        span: Span,
+    },
 }
 
 impl ScanningError {
-    pub fn error(self, line: u32) -> Self {
-        self.report(line, "");
-        self
+    /// Scanning errors are numbered E01xx, see [`crate::error::Error::code`]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::At { error, .. } => error.code(),
+            Self::UntermString => "E0101",
+            Self::Syntax => "E0102",
+            Self::FloatParse(_) => "E0103",
+        }
+    }
+
+    /// Attaches where the error happened, errors that already know keep their span
+    pub fn error(self, span: Span) -> Self {
+        match self {
+            located @ Self::At { .. } => located,
+            error => Self::At {
+                error: Box::new(error),
+                span,
+            },
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::At { span, .. } => Some(*span),
+            _ => None,
+        }
     }
 
-    pub fn report(&self, line: u32, whre: &str) {
-        eprintln!("[line {}] at {}: {}", line, whre, self);
+    /// What went wrong, without the location
+    pub fn kind(&self) -> &Self {
+        match self {
+            Self::At { error, .. } => error.kind(),
+            error => error,
+        }
     }
 }
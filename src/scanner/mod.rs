@@ -1,9 +1,11 @@
 mod error;
 mod scanned_token;
+mod span;
 pub use error::ScanningError;
+pub use span::Span;
 
 pub use crate::token::Token;
-pub use scanned_token::ScannedToken;
+pub use scanned_token::{Comment, ScannedToken};
 
 use crate::token::Operator;
 use crate::token::SmartString;
@@ -23,60 +25,110 @@ pub enum ScanResult {
     INVALID,
 }
 
-pub fn scan(source: &str) -> Result<Vec<ScannedToken>> {
+/// Keeps going past errors, giving back all of them in the order they were found
+pub fn scan(source: &str) -> std::result::Result<Vec<ScannedToken>, Vec<ScanningError>> {
+    let scanned = scan_with_comments(source);
+    if scanned.errors.is_empty() {
+        Ok(scanned.tokens)
+    } else {
+        Err(scanned.errors)
+    }
+}
+
+/// Everything the scanner found. The tokens that did scan are kept even when there are errors,
+/// so the parser can still go looking for mistakes of its own
+#[derive(Debug, Default)]
+pub struct Scanned {
+    pub tokens: Vec<ScannedToken>,
+    /// The comments the parser never sees, so they can be put back
+    pub comments: Vec<Comment>,
+    pub errors: Vec<ScanningError>,
+}
+
+/// Like [`scan`], but holds on to the comments and to whatever scanned fine around any errors
+pub fn scan_with_comments(source: &str) -> Scanned {
     let mut tokens = Vec::with_capacity(source.len());
-    let mut err = None;
+    let mut comments = Vec::new();
+    let mut errors = Vec::new();
     let mut line = 1;
+    let mut line_start = 0;
     let mut iter = source.chars();
     let mut cur_slice = iter.as_str();
     while let Some(char) = iter.next() {
+        let start = source.len() - cur_slice.len();
+        let column = (start - line_start + 1) as u32;
+        // a token ends wherever the iterator has got to once it's been scanned
+        let span =
+            move |iter: &Chars| Span::new(line, column, start, source.len() - iter.as_str().len());
         match scan_token(char) {
-            ScanResult::Token(t) => tokens.push(token(t, line)),
-            ScanResult::Operator(o) => tokens.push(token(operator(o, cur_slice, &mut iter), line)),
-            ScanResult::NEWLINE => line += 1,
-            ScanResult::NUMBER(number) => {
-                tokens.push(token(handle_number(&mut iter, number)?, line));
+            ScanResult::Token(t) => tokens.push(token(t, span(&iter))),
+            ScanResult::Operator(o) => {
+                let operator = operator(o, cur_slice, &mut iter);
+                tokens.push(token(operator, span(&iter)))
             }
+            ScanResult::NEWLINE => {
+                line += 1;
+                line_start = start + 1;
+            }
+            ScanResult::NUMBER(number) => match handle_number(&mut iter, number) {
+                Ok(number) => tokens.push(token(number, span(&iter))),
+                Err(e) => errors.push(e.error(span(&iter))),
+            },
             ScanResult::IDENTIFIER(letter) => {
-                tokens.push(token(handle_identifier(&mut iter, letter), line))
+                let identifier = handle_identifier(&mut iter, letter);
+                tokens.push(token(identifier, span(&iter)))
             }
             ScanResult::SLASH => {
                 if let Some('/') = peek(&iter) {
                     advance_while(&mut iter, |&x| x != '\n');
+                    let text = source[start..source.len() - iter.as_str().len()].trim_end();
+                    let span = Span::new(line, column, start, start + text.len());
+                    comments.push(Comment::new(text, span));
                 } else {
-                    tokens.push(token(Token::SLASH, line));
+                    tokens.push(token(Token::SLASH, span(&iter)));
                 }
             }
             ScanResult::STRING => {
                 let remaining = iter.as_str().len();
                 match slice_while(&mut iter, |&x| x != '"') {
-                    None => tokens.push(token(Token::STRING(SmartString::new()), line)),
+                    None => {
+                        iter.next();
+                        tokens.push(token(Token::STRING(SmartString::new()), span(&iter)))
+                    }
                     Some(slice) if slice.len() == remaining => {
-                        ScanningError::UntermString.error(line);
-                        err = Some(ScanningError::UntermString);
+                        errors.push(ScanningError::UntermString.error(span(&iter)));
+                    }
+                    Some(slice) => {
+                        // go past the closing quote so it's part of the token
+                        iter.next();
+                        let mut span = span(&iter);
+                        // strings can run over several lines
+                        if let Some(newline) = slice.rfind('\n') {
+                            line += slice.matches('\n').count() as u32;
+                            line_start = start + 1 + newline + 1;
+                            span = span.ending(line, (span.end - line_start + 1) as u32);
+                        }
+                        tokens.push(string(slice, span));
                     }
-                    Some(slice) => tokens.push(string(slice, line)),
                 }
-                iter.next();
-                // if it's "" or "*" go past the last quote, otherwise it'll just be None
             }
             ScanResult::WHITESPACE => {}
             ScanResult::INVALID => {
-                ScanningError::Syntax.error(line);
-                err = Some(ScanningError::Syntax);
+                errors.push(ScanningError::Syntax.error(span(&iter)));
             }
         }
         cur_slice = iter.as_str();
     }
-    match err {
-        Some(error) => Err(error),
-        None => Ok(tokens),
+    Scanned {
+        tokens,
+        comments,
+        errors,
     }
 }
 
-fn string(source: &str, line: u32) -> ScannedToken {
+fn string(source: &str, span: Span) -> ScannedToken {
     let token = handle_string(source);
-    ScannedToken::new(token, line)
+    ScannedToken::new(token, span)
 }
 
 fn operator(operator: Operator, source: &str, iter: &mut Chars) -> Token {
@@ -94,7 +146,10 @@ fn scan_token(char: char) -> ScanResult {
         ')' => ScanResult::Token(Token::RIGHTPAREN),
         '{' => ScanResult::Token(Token::LEFTBRACE),
         '}' => ScanResult::Token(Token::RIGHTBRACE),
+        '[' => ScanResult::Token(Token::LEFTBRACKET),
+        ']' => ScanResult::Token(Token::RIGHTBRACKET),
         ',' => ScanResult::Token(Token::COMMA),
+        ':' => ScanResult::Token(Token::COLON),
         '.' => ScanResult::Token(Token::DOT),
         '-' => ScanResult::Token(Token::MINUS),
         '+' => ScanResult::Token(Token::PLUS),
@@ -109,19 +164,19 @@ fn scan_token(char: char) -> ScanResult {
         '/' => ScanResult::SLASH,
         '"' => ScanResult::STRING,
         number if number.is_ascii_digit() => ScanResult::NUMBER(number),
-        letter if letter.is_alphabetic() => ScanResult::IDENTIFIER(letter),
+        letter if is_identifier_start(letter) => ScanResult::IDENTIFIER(letter),
         _ => ScanResult::INVALID,
     }
 }
 
-fn token(token: Token, line: u32) -> ScannedToken {
-    ScannedToken::new(token, line)
+fn token(token: Token, span: Span) -> ScannedToken {
+    ScannedToken::new(token, span)
 }
 
 fn handle_identifier(iter: &mut Chars, letter: char) -> Token {
     let mut literal = SmartString::new();
     literal.push(letter);
-    if let Some(slice) = slice_while(iter, char::is_ascii_alphabetic) {
+    if let Some(slice) = slice_while(iter, |&x| is_identifier_start(x) || x.is_alphanumeric()) {
         literal.push_str(slice);
     }
     if let Some(keyword) = Token::from_keyword(&literal) {
@@ -131,6 +186,11 @@ fn handle_identifier(iter: &mut Chars, letter: char) -> Token {
     }
 }
 
+// any unicode letter can start an identifier, and letters or digits from any script can follow
+fn is_identifier_start(letter: char) -> bool {
+    letter.is_alphabetic() || letter == '_'
+}
+
 fn handle_number(iter: &mut Chars, number: char) -> Result<Token> {
     let mut lexeme = SmartString::new();
     lexeme.push(number);
@@ -147,13 +207,14 @@ fn handle_number(iter: &mut Chars, number: char) -> Result<Token> {
     Ok(Token::NUMBER(value))
 }
 
+/// Returns how many bytes were skipped, so the result can be used to slice the source
 fn advance_while<F>(iter: &mut Chars, f: F) -> usize
 where
     F: Fn(&char) -> bool,
 {
     let mut amount = 0;
-    while peek(iter).is_some_and(|x| f(&x)) {
-        amount += 1;
+    while let Some(char) = peek(iter).filter(|x| f(x)) {
+        amount += char.len_utf8();
         iter.next();
     }
     amount
@@ -253,6 +314,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn keep_comments() {
+        let scanned = scan_with_comments("// first\r\nprint 1; // second  \n");
+        assert!(scanned.errors.is_empty());
+        assert_eq!(scanned.tokens.len(), 3);
+        let comments: Vec<_> = scanned
+            .comments
+            .iter()
+            .map(|x| (x.text.as_str(), x.span.line, x.span.column))
+            .collect();
+        assert_eq!(comments, [("// first", 1, 1), ("// second", 2, 10)]);
+    }
+
     #[test]
     fn identifier() {
         compare_one("ababa", IDENTIFIER("ababa".into()))
@@ -270,7 +344,67 @@ mod tests {
 
     #[test]
     fn unterm_string() {
-        assert!(scan("\"unterminated moment").is_err_and(|e| e == ScanningError::UntermString))
+        assert!(scan("\"unterminated moment")
+            .is_err_and(|e| e[0].kind() == &ScanningError::UntermString))
+    }
+
+    #[test]
+    fn scan_utf8() {
+        compare_scan(
+            "// ça va? 🎉\nprint \"héllo, 世界\" + naïve_2;",
+            vec![
+                PRINT,
+                STRING("héllo, 世界".into()),
+                PLUS,
+                IDENTIFIER("naïve_2".into()),
+                SEMICOLON,
+            ],
+        );
+    }
+
+    #[test]
+    fn token_spans() {
+        let source = "var x =\n  \"a\nb\" + 1;";
+        let spans: Vec<_> = scan(source)
+            .unwrap()
+            .into_iter()
+            .map(|x| {
+                (
+                    x.span.line,
+                    x.span.column,
+                    &source[x.span.start..x.span.end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (1, 1, "var"),
+                (1, 5, "x"),
+                (1, 7, "="),
+                (2, 3, "\"a\nb\""),
+                (3, 4, "+"),
+                (3, 6, "1"),
+                (3, 7, ";"),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_error() {
+        let errors = scan("var a = #;\nprint @ + 1;\n\"oops").unwrap_err();
+        let found: Vec<_> = errors
+            .iter()
+            .map(|e| (e.kind(), e.span().unwrap().line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (&ScanningError::Syntax, 1),
+                (&ScanningError::Syntax, 2),
+                (&ScanningError::UntermString, 3),
+            ]
+        );
     }
 
     #[test]
@@ -1,3 +1,4 @@
+use super::Span;
 pub use crate::token::Token;
 use std::fmt;
 /// The Token struct and funcitonality
@@ -5,18 +6,34 @@ use std::fmt;
 pub struct ScannedToken {
     /// What the token this is, also stores value
     pub type_: Token,
-    /// Line number of the lexeme
-    pub line: u32,
+    /// Where the lexeme is in the source
+    pub span: Span,
 }
 
 impl ScannedToken {
-    pub fn new(type_: Token, line: u32) -> Self {
-        Self { type_, line }
+    pub fn new(type_: Token, span: Span) -> Self {
+        Self { type_, span }
     }
 }
 
 impl fmt::Display for ScannedToken {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", self.line, self.type_)
+        write!(f, "{}: {}", self.span, self.type_)
+    }
+}
+
+/// A `//` comment, from the slashes to the end of the line, trailing whitespace aside
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+impl Comment {
+    pub fn new(text: &str, span: Span) -> Self {
+        Self {
+            text: text.to_string(),
+            span,
+        }
     }
 }
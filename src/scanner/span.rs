@@ -0,0 +1,62 @@
+use std::fmt;
+
+/// Where a piece of source came from. `start` and `end` are byte offsets into the source, and
+/// `line` and `column` are 1-based with the column counted in bytes too, so a span can always be
+/// turned back into a slice of the line it starts on. `end_line` and `end_column` are where the
+/// byte just past the end sits
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub line: u32,
+    pub column: u32,
+    pub start: usize,
+    pub end: usize,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+impl Span {
+    /// A span that doesn't leave the line it starts on
+    pub fn new(line: u32, column: u32, start: usize, end: usize) -> Self {
+        Self {
+            line,
+            column,
+            start,
+            end,
+            end_line: line,
+            end_column: column + (end - start) as u32,
+        }
+    }
+
+    /// The same span, but ending on a later line
+    pub fn ending(self, line: u32, column: u32) -> Self {
+        Self {
+            end_line: line,
+            end_column: column,
+            ..self
+        }
+    }
+
+    /// Covers everything from the start of this span to the end of the other one
+    pub fn to(self, other: Span) -> Self {
+        if other.end <= self.end {
+            return self;
+        }
+        Self {
+            end: other.end,
+            end_line: other.end_line,
+            end_column: other.end_column,
+            ..self
+        }
+    }
+
+    /// The empty span just past the end of this one, for pointing at something that's missing
+    pub fn after(self) -> Self {
+        Self::new(self.end_line, self.end_column, self.end, self.end)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
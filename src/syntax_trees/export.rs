@@ -0,0 +1,471 @@
+use super::expression::Expression;
+use super::lox_object::LoxObject;
+use super::statement::{Function, Statement};
+use crate::diagnostics::json_string;
+use crate::scanner::Span;
+use std::fmt::Write;
+
+/// How `lox ast` prints the tree, picked with `--json` or `--sexpr`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AstFormat {
+    /// The `Display` impls, compact but not meant to be read back
+    #[default]
+    Text,
+    Json,
+    Sexpr,
+}
+
+/// The whole program as a JSON array on one line. Every node is an object with its `kind`
+/// and the `line` and `column` it starts at, children are named fields holding nodes or arrays
+/// of them, and anything missing is null
+pub fn to_json(statements: &[Statement]) -> String {
+    array(statements.iter().map(Statement::to_json))
+}
+
+/// A statement per line, parenthesized like the book's `AstPrinter`
+pub fn to_sexpr(statements: &[Statement]) -> String {
+    statements
+        .iter()
+        .map(|statement| statement.to_sexpr() + "\n")
+        .collect()
+}
+
+impl Statement {
+    pub fn to_json(&self) -> String {
+        let span = self.span();
+        match self {
+            Self::Expression { expression, .. } => {
+                node("Expression", span, &[("expression", expression.to_json())])
+            }
+            Self::Print { expression, .. } => {
+                node("Print", span, &[("expression", expression.to_json())])
+            }
+            Self::Var {
+                name, initializer, ..
+            } => node(
+                "Var",
+                span,
+                &[
+                    ("name", json_string(name)),
+                    ("initializer", optional(initializer.as_ref())),
+                ],
+            ),
+            Self::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => node(
+                "While",
+                span,
+                &[
+                    ("condition", condition.to_json()),
+                    ("body", body.to_json()),
+                    ("increment", optional(increment.as_ref())),
+                ],
+            ),
+            Self::Function(function) => function.to_json(),
+            Self::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => node(
+                "Class",
+                span,
+                &[
+                    ("name", json_string(name.as_ref())),
+                    ("superclass", optional(superclass.as_ref())),
+                    ("methods", array(methods.iter().map(Function::to_json))),
+                ],
+            ),
+            Self::If {
+                condition,
+                then,
+                else_case,
+                ..
+            } => node(
+                "If",
+                span,
+                &[
+                    ("condition", condition.to_json()),
+                    ("then", then.to_json()),
+                    (
+                        "else",
+                        else_case
+                            .as_ref()
+                            .map_or_else(|| "null".to_string(), |x| x.to_json()),
+                    ),
+                ],
+            ),
+            Self::Block { statements, .. } => node(
+                "Block",
+                span,
+                &[("statements", array(statements.iter().map(Self::to_json)))],
+            ),
+            Self::Return { value, .. } => {
+                node("Return", span, &[("value", optional(value.as_ref()))])
+            }
+            Self::Break { .. } => node("Break", span, &[]),
+            Self::Continue { .. } => node("Continue", span, &[]),
+        }
+    }
+
+    pub fn to_sexpr(&self) -> String {
+        match self {
+            Self::Expression { expression, .. } => parenthesize(";", [expression.to_sexpr()]),
+            Self::Print { expression, .. } => parenthesize("print", [expression.to_sexpr()]),
+            Self::Var {
+                name,
+                initializer: Some(initializer),
+                ..
+            } => parenthesize(
+                "var",
+                [name.to_string(), "=".to_string(), initializer.to_sexpr()],
+            ),
+            Self::Var { name, .. } => parenthesize("var", [name.to_string()]),
+            // the increment only shows up for loops that were written with for
+            Self::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => parenthesize(
+                "while",
+                [condition.to_sexpr(), body.to_sexpr()]
+                    .into_iter()
+                    .chain(increment.as_ref().map(Expression::to_sexpr)),
+            ),
+            Self::Function(function) => function.to_sexpr(),
+            Self::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => {
+                let superclass = superclass
+                    .as_ref()
+                    .map(|superclass| format!("< {}", superclass.to_sexpr()));
+                parenthesize(
+                    "class",
+                    std::iter::once(name.to_string())
+                        .chain(superclass)
+                        .chain(methods.iter().map(Function::to_sexpr)),
+                )
+            }
+            Self::If {
+                condition,
+                then,
+                else_case,
+                ..
+            } => parenthesize(
+                "if",
+                [condition.to_sexpr(), then.to_sexpr()]
+                    .into_iter()
+                    .chain(else_case.as_ref().map(|x| x.to_sexpr())),
+            ),
+            Self::Block { statements, .. } => {
+                parenthesize("block", statements.iter().map(Self::to_sexpr))
+            }
+            Self::Return { value, .. } => {
+                parenthesize("return", value.as_ref().map(Expression::to_sexpr))
+            }
+            Self::Break { .. } => "(break)".to_string(),
+            Self::Continue { .. } => "(continue)".to_string(),
+        }
+    }
+}
+
+impl Function {
+    pub fn to_json(&self) -> String {
+        let params = self.params.iter().map(|x| json_string(x.as_ref()));
+        node(
+            "Function",
+            self.span,
+            &[
+                ("name", json_string(self.name.as_ref())),
+                ("params", array(params)),
+                ("body", array(self.body.iter().map(Statement::to_json))),
+            ],
+        )
+    }
+
+    pub fn to_sexpr(&self) -> String {
+        let params: Vec<_> = self.params.iter().map(ToString::to_string).collect();
+        parenthesize(
+            "fun",
+            [format!("{}({})", self.name, params.join(" "))]
+                .into_iter()
+                .chain(self.body.iter().map(Statement::to_sexpr)),
+        )
+    }
+}
+
+impl Expression {
+    pub fn to_json(&self) -> String {
+        let span = self.span();
+        match self {
+            Self::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => node(
+                "Binary",
+                span,
+                &[
+                    ("operator", json_string(&operator.to_string())),
+                    ("left", left.to_json()),
+                    ("right", right.to_json()),
+                ],
+            ),
+            Self::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => node(
+                "Logical",
+                span,
+                &[
+                    ("operator", json_string(&operator.to_string())),
+                    ("left", left.to_json()),
+                    ("right", right.to_json()),
+                ],
+            ),
+            Self::Grouping { inner, .. } => {
+                node("Grouping", span, &[("expression", inner.to_json())])
+            }
+            Self::Literal { value, .. } => node("Literal", span, &[("value", value.to_json())]),
+            Self::Call { callee, args, .. } => node(
+                "Call",
+                span,
+                &[
+                    ("callee", callee.to_json()),
+                    ("arguments", array(args.iter().map(Self::to_json))),
+                ],
+            ),
+            Self::Unary {
+                operator, inner, ..
+            } => node(
+                "Unary",
+                span,
+                &[
+                    ("operator", json_string(&operator.to_string())),
+                    ("operand", inner.to_json()),
+                ],
+            ),
+            Self::Variable { name, .. } => node("Variable", span, &[("name", json_string(name))]),
+            Self::Assign { name, value, .. } => node(
+                "Assign",
+                span,
+                &[("name", json_string(name)), ("value", value.to_json())],
+            ),
+            Self::Get { object, name, .. } => node(
+                "Get",
+                span,
+                &[("object", object.to_json()), ("name", json_string(name))],
+            ),
+            Self::Set {
+                object,
+                name,
+                value,
+                ..
+            } => node(
+                "Set",
+                span,
+                &[
+                    ("object", object.to_json()),
+                    ("name", json_string(name)),
+                    ("value", value.to_json()),
+                ],
+            ),
+            Self::This { .. } => node("This", span, &[]),
+            Self::Super { method, .. } => node("Super", span, &[("method", json_string(method))]),
+            Self::List { elements, .. } => node(
+                "List",
+                span,
+                &[("elements", array(elements.iter().map(Self::to_json)))],
+            ),
+            Self::Map { entries, .. } => {
+                let entries = entries.iter().map(|(key, value)| {
+                    format!(
+                        "{{\"key\":{},\"value\":{}}}",
+                        key.to_json(),
+                        value.to_json()
+                    )
+                });
+                node("Map", span, &[("entries", array(entries))])
+            }
+            Self::Index { object, index, .. } => node(
+                "Index",
+                span,
+                &[("object", object.to_json()), ("index", index.to_json())],
+            ),
+            Self::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => node(
+                "SetIndex",
+                span,
+                &[
+                    ("object", object.to_json()),
+                    ("index", index.to_json()),
+                    ("value", value.to_json()),
+                ],
+            ),
+        }
+    }
+
+    pub fn to_sexpr(&self) -> String {
+        match self {
+            Self::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => parenthesize(&operator.to_string(), [left.to_sexpr(), right.to_sexpr()]),
+            Self::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => parenthesize(&operator.to_string(), [left.to_sexpr(), right.to_sexpr()]),
+            Self::Grouping { inner, .. } => parenthesize("group", [inner.to_sexpr()]),
+            Self::Literal { value, .. } => value.to_sexpr(),
+            Self::Call { callee, args, .. } => parenthesize(
+                "call",
+                std::iter::once(callee.to_sexpr()).chain(args.iter().map(Self::to_sexpr)),
+            ),
+            Self::Unary {
+                operator, inner, ..
+            } => parenthesize(&operator.to_string(), [inner.to_sexpr()]),
+            Self::Variable { name, .. } => name.to_string(),
+            Self::Assign { name, value, .. } => {
+                parenthesize("=", [name.to_string(), value.to_sexpr()])
+            }
+            Self::Get { object, name, .. } => {
+                parenthesize(".", [object.to_sexpr(), name.to_string()])
+            }
+            Self::Set {
+                object,
+                name,
+                value,
+                ..
+            } => parenthesize("=", [object.to_sexpr(), name.to_string(), value.to_sexpr()]),
+            Self::This { .. } => "this".to_string(),
+            Self::Super { method, .. } => parenthesize("super", [method.to_string()]),
+            Self::List { elements, .. } => {
+                parenthesize("list", elements.iter().map(Self::to_sexpr))
+            }
+            Self::Map { entries, .. } => parenthesize(
+                "map",
+                entries
+                    .iter()
+                    .map(|(key, value)| format!("({} {})", key.to_sexpr(), value.to_sexpr())),
+            ),
+            Self::Index { object, index, .. } => {
+                parenthesize("[]", [object.to_sexpr(), index.to_sexpr()])
+            }
+            Self::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => parenthesize(
+                "[]=",
+                [object.to_sexpr(), index.to_sexpr(), value.to_sexpr()],
+            ),
+        }
+    }
+}
+
+impl LoxObject {
+    /// Literals map onto JSON's own types. Runtime values like functions can't come out of the
+    /// parser, they're written as their `Display` string all the same
+    pub fn to_json(&self) -> String {
+        match self {
+            Self::Float(value) if value.is_finite() => value.to_string(),
+            Self::Float(_) | Self::Nil => "null".to_string(),
+            Self::Bool(value) => value.to_string(),
+            Self::String(string) => json_string(string),
+            other => json_string(&other.to_string()),
+        }
+    }
+
+    pub fn to_sexpr(&self) -> String {
+        match self {
+            Self::String(string) => format!("\"{string}\""),
+            other => other.to_string(),
+        }
+    }
+}
+
+fn node(kind: &str, span: Span, fields: &[(&str, String)]) -> String {
+    let mut out = format!(
+        "{{\"kind\":{},\"line\":{},\"column\":{}",
+        json_string(kind),
+        span.line,
+        span.column
+    );
+    for (name, value) in fields {
+        // writing to a String can't fail
+        let _ = write!(out, ",{}:{value}", json_string(name));
+    }
+    out.push('}');
+    out
+}
+
+fn array(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<_>>().join(","))
+}
+
+fn optional(expression: Option<&Expression>) -> String {
+    expression.map_or_else(|| "null".to_string(), Expression::to_json)
+}
+
+fn parenthesize(name: &str, parts: impl IntoIterator<Item = String>) -> String {
+    let mut out = format!("({name}");
+    for part in parts {
+        out.push(' ');
+        out.push_str(&part);
+    }
+    out.push(')');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner;
+
+    fn parse(source: &str) -> Vec<Statement> {
+        Parser::new(scanner::scan(source).unwrap()).parse().unwrap()
+    }
+
+    #[test]
+    fn sexpr() {
+        assert_eq!(
+            to_sexpr(&parse("-123 * (45.67);")),
+            "(; (* (- 123) (group 45.67)))\n"
+        );
+        assert_eq!(
+            to_sexpr(&parse(
+                "class B < A { f(x) { return super.f(x)[0]; } }\nfor (var i = 0; i < 2; i = i + 1) print \"i\";"
+            )),
+            "(class B < A (fun f(x) (return ([] (call (super f) x) 0))))\n\
+             (block (var i = 0) (while (< i 2) (print \"i\") (= i (+ i 1))))\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            to_json(&parse("var a = {\"k\": nil};\nif (!a) a.b = 1.5;")),
+            r#"[{"kind":"Var","line":1,"column":1,"name":"a","initializer":{"kind":"Map","line":1,"column":9,"entries":[{"key":{"kind":"Literal","line":1,"column":10,"value":"k"},"value":{"kind":"Literal","line":1,"column":15,"value":null}}]}},{"kind":"If","line":2,"column":1,"condition":{"kind":"Unary","line":2,"column":5,"operator":"!","operand":{"kind":"Variable","line":2,"column":6,"name":"a"}},"then":{"kind":"Expression","line":2,"column":9,"expression":{"kind":"Set","line":2,"column":9,"object":{"kind":"Variable","line":2,"column":9,"name":"a"},"name":"b","value":{"kind":"Literal","line":2,"column":15,"value":1.5}}},"else":null}]"#
+        );
+    }
+}
@@ -1,44 +1,121 @@
 use super::lox_object::LoxObject;
-use crate::scanner::ScannedToken;
+use crate::scanner::Span;
 use crate::token::SmartString;
 use std::fmt;
 use strum_macros::Display;
 
 pub type Result<T> = std::result::Result<T, crate::interpreter::RuntimeError>;
 
+/// Every variant has a span covering all of its source, from the first token to the last
 #[derive(Clone, Debug)]
 pub enum Expression {
     Binary {
         left: Box<Expression>,
         operator: BinaryOperator,
         right: Box<Expression>,
+        span: Span,
     },
     Logical {
         left: Box<Expression>,
         operator: LogicalOperator,
         right: Box<Expression>,
+        span: Span,
+    },
+    Grouping {
+        inner: Box<Expression>,
+        span: Span,
+    },
+    Literal {
+        value: LoxObject,
+        span: Span,
     },
-    Grouping(Box<Expression>),
-    Literal(LoxObject),
     Call {
         callee: Box<Expression>,
-        paren: ScannedToken,
         args: Vec<Expression>,
+        span: Span,
     },
     Unary {
         operator: UnaryOperator,
         inner: Box<Expression>,
+        span: Span,
     },
+    /// depth is how many scopes up the binding lives, filled in by the resolver and left as
+    /// None for globals
     Variable {
         name: SmartString,
-        line: u32,
+        span: Span,
+        depth: Option<usize>,
     },
     Assign {
         name: SmartString,
         value: Box<Expression>,
+        span: Span,
+        depth: Option<usize>,
+    },
+    Get {
+        object: Box<Expression>,
+        name: SmartString,
+        span: Span,
+    },
+    Set {
+        object: Box<Expression>,
+        name: SmartString,
+        value: Box<Expression>,
+        span: Span,
+    },
+    This {
+        span: Span,
+        depth: Option<usize>,
+    },
+    Super {
+        method: SmartString,
+        span: Span,
+        depth: Option<usize>,
+    },
+    List {
+        elements: Vec<Expression>,
+        span: Span,
+    },
+    Map {
+        entries: Vec<(Expression, Expression)>,
+        span: Span,
+    },
+    Index {
+        object: Box<Expression>,
+        index: Box<Expression>,
+        span: Span,
+    },
+    SetIndex {
+        object: Box<Expression>,
+        index: Box<Expression>,
+        value: Box<Expression>,
+        span: Span,
     },
 }
 
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Binary { span, .. }
+            | Self::Logical { span, .. }
+            | Self::Grouping { span, .. }
+            | Self::Literal { span, .. }
+            | Self::Call { span, .. }
+            | Self::Unary { span, .. }
+            | Self::Variable { span, .. }
+            | Self::Assign { span, .. }
+            | Self::Get { span, .. }
+            | Self::Set { span, .. }
+            | Self::This { span, .. }
+            | Self::Super { span, .. }
+            | Self::List { span, .. }
+            | Self::Map { span, .. }
+            | Self::Index { span, .. }
+            | Self::SetIndex { span, .. } => *span,
+        }
+    }
+}
+
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -46,29 +123,69 @@ impl fmt::Display for Expression {
                 left,
                 operator,
                 right,
+                ..
             } => write!(f, "({}{}{})", left, operator, right),
             Self::Logical {
                 left,
                 operator,
                 right,
+                ..
             } => write!(f, "({}{}{})", left, operator, right),
-            Self::Grouping(e) => write!(f, "({})", e),
-            Self::Literal(o) => write!(f, "{}", o),
+            Self::Grouping { inner, .. } => write!(f, "({})", inner),
+            Self::Literal { value, .. } => write!(f, "{}", value),
             Self::Call { callee, args, .. } => {
                 write!(f, "{callee}(")?;
-                if let Some(last) = args.last() {
-                    for arg in &args[..args.len() - 1] {
-                        write!(f, "{arg},")?;
-                    }
-                    write!(f, "{last}")?;
-                }
+                format_list(f, args)?;
                 write!(f, ")")
             }
-            Self::Unary { operator, inner } => write!(f, "({operator}{inner})"),
+            Self::Unary {
+                operator, inner, ..
+            } => write!(f, "({operator}{inner})"),
             Self::Variable { name: var, .. } => write!(f, "({var})"),
-            Self::Assign { name, value } => write!(f, "({name} = {value})"),
+            Self::Assign { name, value, .. } => write!(f, "({name} = {value})"),
+            Self::Get { object, name, .. } => write!(f, "{object}.{name}"),
+            Self::Set {
+                object,
+                name,
+                value,
+                ..
+            } => write!(f, "({object}.{name} = {value})"),
+            Self::This { .. } => write!(f, "this"),
+            Self::Super { method, .. } => write!(f, "super.{method}"),
+            Self::List { elements, .. } => {
+                write!(f, "[")?;
+                format_list(f, elements)?;
+                write!(f, "]")
+            }
+            Self::Map { entries, .. } => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{key}:{value}")?;
+                }
+                write!(f, "}}")
+            }
+            Self::Index { object, index, .. } => write!(f, "{object}[{index}]"),
+            Self::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => write!(f, "({object}[{index}] = {value})"),
+        }
+    }
+}
+
+fn format_list(f: &mut fmt::Formatter, list: &[Expression]) -> fmt::Result {
+    if let Some(last) = list.last() {
+        for element in &list[..list.len() - 1] {
+            write!(f, "{element},")?;
         }
+        write!(f, "{last}")?;
     }
+    Ok(())
 }
 
 /*
@@ -77,30 +194,6 @@ struct Call_able {}
 impl TryInto<Call_able> for Expression {}
 */
 
-impl From<f64> for Expression {
-    fn from(float: f64) -> Self {
-        Self::Literal(LoxObject::Float(float))
-    }
-}
-
-impl From<bool> for Expression {
-    fn from(boolean: bool) -> Self {
-        Self::Literal(LoxObject::Bool(boolean))
-    }
-}
-
-impl From<SmartString> for Expression {
-    fn from(string: SmartString) -> Self {
-        Self::Literal(LoxObject::String(string))
-    }
-}
-
-impl Expression {
-    pub fn nil() -> Self {
-        Self::Literal(LoxObject::Nil)
-    }
-}
-
 #[derive(Clone, Copy, Display, Debug)]
 pub enum UnaryOperator {
     #[strum(serialize = "!")]
@@ -112,25 +205,25 @@ pub enum UnaryOperator {
 #[derive(Copy, Clone, Display, Debug)]
 pub enum BinaryOperator {
     #[strum(serialize = "==")]
-    EQUALEQUAL(u32),
+    EQUALEQUAL,
     #[strum(serialize = "!=")]
-    BANGEQUAL(u32),
+    BANGEQUAL,
     #[strum(serialize = ">")]
-    GREATER(u32),
+    GREATER,
     #[strum(serialize = ">=")]
-    GREATEREQUAL(u32),
+    GREATEREQUAL,
     #[strum(serialize = "<")]
-    LESS(u32),
+    LESS,
     #[strum(serialize = "<=")]
-    LESSEQUAL(u32),
+    LESSEQUAL,
     #[strum(serialize = "+")]
-    PLUS(u32),
+    PLUS,
     #[strum(serialize = "-")]
-    MINUS(u32),
+    MINUS,
     #[strum(serialize = "*")]
-    STAR(u32),
+    STAR,
     #[strum(serialize = "/")]
-    SLASH(u32),
+    SLASH,
 }
 
 #[derive(Copy, Clone, Display, Debug, PartialEq, Eq)]
@@ -156,18 +249,18 @@ use crate::token::Token;
 impl BinaryOperator {
     // trying really hard to prefer duplication to the wrong abstraction here
 
-    pub fn from_token(token: ScannedToken) -> Option<Self> {
-        match token.type_ {
-            Token::EQUALEQUAL => Some(Self::EQUALEQUAL(token.line)),
-            Token::BANGEQUAL => Some(Self::BANGEQUAL(token.line)),
-            Token::GREATER => Some(Self::GREATER(token.line)),
-            Token::GREATEREQUAL => Some(Self::GREATEREQUAL(token.line)),
-            Token::LESS => Some(Self::LESS(token.line)),
-            Token::LESSEQUAL => Some(Self::LESSEQUAL(token.line)),
-            Token::PLUS => Some(Self::PLUS(token.line)),
-            Token::MINUS => Some(Self::MINUS(token.line)),
-            Token::STAR => Some(Self::STAR(token.line)),
-            Token::SLASH => Some(Self::SLASH(token.line)),
+    pub fn from_token(token: &Token) -> Option<Self> {
+        match token {
+            Token::EQUALEQUAL => Some(Self::EQUALEQUAL),
+            Token::BANGEQUAL => Some(Self::BANGEQUAL),
+            Token::GREATER => Some(Self::GREATER),
+            Token::GREATEREQUAL => Some(Self::GREATEREQUAL),
+            Token::LESS => Some(Self::LESS),
+            Token::LESSEQUAL => Some(Self::LESSEQUAL),
+            Token::PLUS => Some(Self::PLUS),
+            Token::MINUS => Some(Self::MINUS),
+            Token::STAR => Some(Self::STAR),
+            Token::SLASH => Some(Self::SLASH),
             _ => None,
         }
     }
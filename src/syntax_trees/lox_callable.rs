@@ -0,0 +1,67 @@
+use super::lox_object::LoxObject;
+use crate::interpreter::RuntimeError;
+use crate::token::SmartString;
+use std::fmt;
+use std::rc::Rc;
+
+pub type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// Anything the interpreter can call without walking a Lox function body
+pub trait LoxCallable {
+    fn arity(&self) -> usize;
+    fn name(&self) -> &str;
+    fn call(&self, args: Vec<LoxObject>) -> Result<LoxObject>;
+}
+
+type NativeFn = dyn Fn(Vec<LoxObject>) -> Result<LoxObject>;
+
+/// A function implemented in Rust, args are checked against arity before it gets called
+#[derive(Clone)]
+pub struct NativeFunction {
+    name: SmartString,
+    arity: usize,
+    function: Rc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: impl Into<SmartString>,
+        arity: usize,
+        function: impl Fn(Vec<LoxObject>) -> Result<LoxObject> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            arity,
+            function: Rc::new(function),
+        }
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(&self, args: Vec<LoxObject>) -> Result<LoxObject> {
+        (self.function)(args)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
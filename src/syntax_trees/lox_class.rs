@@ -0,0 +1,104 @@
+use super::lox_object::LoxObject;
+use crate::interpreter::RuntimeError;
+use crate::token::SmartString;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+pub type Result<T> = std::result::Result<T, RuntimeError>;
+
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: SmartString,
+    pub superclass: Option<Rc<LoxClass>>,
+    // closures over the scope the class was declared in, bound to an instance when looked up
+    methods: HashMap<SmartString, LoxObject>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: SmartString,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<SmartString, LoxObject>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks the method up through the inheritance chain, also returning the superclass of
+    /// whichever class declared it so that `super` inside the method resolves statically
+    pub fn find_method(&self, name: &str) -> Option<(&LoxObject, Option<&Rc<LoxClass>>)> {
+        match self.methods.get(name) {
+            Some(method) => Some((method, self.superclass.as_ref())),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+
+    pub fn bind(&self, name: &str, receiver: &LoxInstance) -> Option<LoxObject> {
+        let (method, superclass) = self.find_method(name)?;
+        Some(LoxObject::BoundMethod {
+            receiver: receiver.clone(),
+            method: Box::new(method.clone()),
+            superclass: superclass.cloned(),
+        })
+    }
+
+    pub fn arity(&self) -> usize {
+        match self.find_method("init") {
+            Some((LoxObject::Closure { declaration, .. }, _)) => declaration.params.len(),
+            Some((LoxObject::VmClosure(closure), _)) => closure.function.arity,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Instances share their fields between clones, so a copy pulled out of a variable still points
+/// at the same object
+#[derive(Clone, Debug)]
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: Rc<RefCell<HashMap<SmartString, LoxObject>>>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: Rc::default(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<LoxObject> {
+        if let Some(field) = self.fields.borrow().get(name) {
+            return Ok(field.clone());
+        }
+
+        self.class
+            .bind(name, self)
+            .ok_or_else(|| RuntimeError::UndefinedProperty(name.into()))
+    }
+
+    pub fn set(&self, name: SmartString, value: LoxObject) {
+        self.fields.borrow_mut().insert(name, value);
+    }
+
+    pub fn same(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.fields, &other.fields)
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} instance", self.class)
+    }
+}
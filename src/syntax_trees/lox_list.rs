@@ -0,0 +1,102 @@
+use super::lox_object::LoxObject;
+use crate::interpreter::RuntimeError;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// Lists are shared like instances, copying one around still points at the same elements
+#[derive(Clone, Debug, Default)]
+pub struct LoxList {
+    elements: Rc<RefCell<Vec<LoxObject>>>,
+}
+
+impl LoxList {
+    pub fn new(elements: Vec<LoxObject>) -> Self {
+        Self {
+            elements: Rc::new(RefCell::new(elements)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.borrow().is_empty()
+    }
+
+    pub fn get(&self, index: &LoxObject) -> Result<LoxObject> {
+        let index = self.index(index, self.len())?;
+        Ok(self.elements.borrow()[index].clone())
+    }
+
+    pub fn set(&self, index: &LoxObject, value: LoxObject) -> Result<()> {
+        let index = self.index(index, self.len())?;
+        self.elements.borrow_mut()[index] = value;
+        Ok(())
+    }
+
+    pub fn push(&self, value: LoxObject) {
+        self.elements.borrow_mut().push(value);
+    }
+
+    /// Popping an empty list gives nil rather than an error
+    pub fn pop(&self) -> LoxObject {
+        self.elements.borrow_mut().pop().unwrap_or(LoxObject::Nil)
+    }
+
+    /// Inserting at the length appends
+    pub fn insert(&self, index: &LoxObject, value: LoxObject) -> Result<()> {
+        let index = self.index(index, self.len() + 1)?;
+        self.elements.borrow_mut().insert(index, value);
+        Ok(())
+    }
+
+    pub fn remove(&self, index: &LoxObject) -> Result<LoxObject> {
+        let index = self.index(index, self.len())?;
+        Ok(self.elements.borrow_mut().remove(index))
+    }
+
+    pub fn same(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.elements, &other.elements)
+    }
+
+    // numbers are floats, so anything fractional or negative can't be an index
+    fn index(&self, index: &LoxObject, bound: usize) -> Result<usize> {
+        let LoxObject::Float(index) = *index else {
+            return Err(RuntimeError::InvalidIndex);
+        };
+        if index.fract() != 0.0 {
+            return Err(RuntimeError::InvalidIndex);
+        }
+        if index < 0.0 || index >= bound as f64 {
+            return Err(RuntimeError::IndexOutOfRange {
+                index,
+                len: self.len(),
+            });
+        }
+        Ok(index as usize)
+    }
+}
+
+impl fmt::Display for LoxList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // a list holding itself would recurse forever
+        let Ok(elements) = self.elements.try_borrow_mut() else {
+            return write!(f, "[...]");
+        };
+        write!(f, "[")?;
+        for (i, element) in elements.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match element {
+                LoxObject::String(string) => write!(f, "\"{string}\"")?,
+                element => write!(f, "{element}")?,
+            }
+        }
+        write!(f, "]")
+    }
+}
@@ -0,0 +1,232 @@
+use super::lox_object::LoxObject;
+use crate::interpreter::RuntimeError;
+use crate::token::SmartString;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// The values that can be used as map keys, they compare the same way the LoxObjects they came
+/// from do
+#[derive(Clone, Debug)]
+pub enum MapKey {
+    Nil,
+    Bool(bool),
+    Float(f64),
+    String(SmartString),
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// NaN never makes it into a key, so float equality is reflexive here
+impl Eq for MapKey {}
+
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Nil => {}
+            Self::Bool(b) => b.hash(state),
+            // 0.0 == -0.0 so they have to hash the same
+            Self::Float(f) if *f == 0.0 => 0.0f64.to_bits().hash(state),
+            Self::Float(f) => f.to_bits().hash(state),
+            Self::String(s) => s.hash(state),
+        }
+    }
+}
+
+impl TryFrom<&LoxObject> for MapKey {
+    type Error = RuntimeError;
+
+    fn try_from(object: &LoxObject) -> Result<Self> {
+        match object {
+            LoxObject::Nil => Ok(Self::Nil),
+            LoxObject::Bool(b) => Ok(Self::Bool(*b)),
+            LoxObject::Float(f) if !f.is_nan() => Ok(Self::Float(*f)),
+            LoxObject::String(s) => Ok(Self::String(s.clone())),
+            _ => Err(RuntimeError::InvalidKey),
+        }
+    }
+}
+
+impl From<MapKey> for LoxObject {
+    fn from(key: MapKey) -> Self {
+        match key {
+            MapKey::Nil => Self::Nil,
+            MapKey::Bool(b) => Self::Bool(b),
+            MapKey::Float(f) => Self::Float(f),
+            MapKey::String(s) => Self::String(s),
+        }
+    }
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::String(s) => write!(f, "\"{s}\""),
+            key => write!(f, "{}", LoxObject::from(key.clone())),
+        }
+    }
+}
+
+// entries remember when they were inserted so keys and printing come out in a stable order
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<MapKey, (u64, LoxObject)>,
+    next: u64,
+}
+
+impl Entries {
+    fn ordered(&self) -> Vec<(&MapKey, &LoxObject)> {
+        let mut entries: Vec<_> = self.map.iter().collect();
+        entries.sort_by_key(|(_, (order, _))| *order);
+        entries
+            .into_iter()
+            .map(|(key, (_, value))| (key, value))
+            .collect()
+    }
+}
+
+/// Maps are shared like lists, copying one around still points at the same entries
+#[derive(Clone, Debug, Default)]
+pub struct LoxMap {
+    entries: Rc<RefCell<Entries>>,
+}
+
+impl LoxMap {
+    pub fn new(entries: Vec<(LoxObject, LoxObject)>) -> Result<Self> {
+        let map = Self::default();
+        for (key, value) in entries {
+            map.set(&key, value)?;
+        }
+        Ok(map)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().map.is_empty()
+    }
+
+    pub fn get(&self, key: &LoxObject) -> Result<LoxObject> {
+        let key = MapKey::try_from(key)?;
+        match self.entries.borrow().map.get(&key) {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(RuntimeError::UndefinedKey(key.to_string().into())),
+        }
+    }
+
+    /// Overwriting a key keeps its original place in the order
+    pub fn set(&self, key: &LoxObject, value: LoxObject) -> Result<()> {
+        let key = MapKey::try_from(key)?;
+        let mut entries = self.entries.borrow_mut();
+        let order = entries.next;
+        match entries.map.get_mut(&key) {
+            Some(entry) => entry.1 = value,
+            None => {
+                entries.map.insert(key, (order, value));
+                entries.next += 1;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has(&self, key: &LoxObject) -> Result<bool> {
+        let key = MapKey::try_from(key)?;
+        Ok(self.entries.borrow().map.contains_key(&key))
+    }
+
+    /// Gives back the removed value, or nil if the key wasn't there
+    pub fn delete(&self, key: &LoxObject) -> Result<LoxObject> {
+        let key = MapKey::try_from(key)?;
+        let removed = self.entries.borrow_mut().map.remove(&key);
+        Ok(removed.map_or(LoxObject::Nil, |(_, value)| value))
+    }
+
+    pub fn keys(&self) -> Vec<LoxObject> {
+        let entries = self.entries.borrow();
+        entries
+            .ordered()
+            .into_iter()
+            .map(|(key, _)| key.clone().into())
+            .collect()
+    }
+
+    pub fn values(&self) -> Vec<LoxObject> {
+        let entries = self.entries.borrow();
+        entries
+            .ordered()
+            .into_iter()
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    pub fn same(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.entries, &other.entries)
+    }
+}
+
+impl fmt::Display for LoxMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // a map holding itself would recurse forever
+        let Ok(entries) = self.entries.try_borrow_mut() else {
+            return write!(f, "{{...}}");
+        };
+        write!(f, "{{")?;
+        for (i, (key, value)) in entries.ordered().into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match value {
+                LoxObject::String(string) => write!(f, "{key}: \"{string}\"")?,
+                value => write!(f, "{key}: {value}")?,
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_follow_equality() {
+        let map = LoxMap::default();
+        map.set(&LoxObject::Float(0.0), LoxObject::Bool(true))
+            .unwrap();
+        assert!(map.has(&LoxObject::Float(-0.0)).unwrap());
+        assert!(!map.has(&LoxObject::String("0".into())).unwrap());
+        assert!(map
+            .set(&LoxObject::Float(f64::NAN), LoxObject::Nil)
+            .is_err());
+    }
+
+    #[test]
+    fn insertion_order() {
+        let map = LoxMap::default();
+        for key in ["b", "a", "c"] {
+            map.set(&LoxObject::String(key.into()), LoxObject::Nil)
+                .unwrap();
+        }
+        map.delete(&LoxObject::String("a".into())).unwrap();
+        map.set(&LoxObject::String("b".into()), LoxObject::Bool(true))
+            .unwrap();
+        assert_eq!(map.to_string(), "{\"b\": true, \"c\": nil}");
+    }
+}
@@ -1,41 +1,85 @@
 use crate::interpreter::RuntimeError;
 use std::cmp;
+use std::fmt;
 use std::ops;
-use strum_macros::Display;
 pub type Result<T> = std::result::Result<T, RuntimeError>;
+use super::lox_callable::NativeFunction;
+use super::lox_class::{LoxClass, LoxInstance};
+use super::lox_list::LoxList;
+use super::lox_map::LoxMap;
 use super::statement::Function;
 use crate::interpreter::Environment;
 use crate::token::SmartString;
+use crate::vm::object::Closure as VmClosure;
+use std::rc::Rc;
 
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug)]
 pub enum LoxObject {
-    #[strum(serialize = "{0}")]
     Float(f64),
-    #[strum(serialize = "{0}")]
     String(SmartString),
-    #[strum(serialize = "{0}")]
     Bool(bool),
-    #[strum(serialize = "nil")]
     Nil,
-    #[strum(serialize = "{0}")]
     VarName(SmartString),
-    #[strum(serialize = "{0}")]
-    Function(Function),
-    #[strum(serialize = "{declaration}{env}")]
     Closure {
         declaration: Function,
         env: Environment,
     },
+    Native(NativeFunction),
+    /// only created by the bytecode vm
+    VmClosure(VmClosure),
+    Class(Rc<LoxClass>),
+    Instance(LoxInstance),
+    BoundMethod {
+        receiver: LoxInstance,
+        method: Box<LoxObject>,
+        superclass: Option<Rc<LoxClass>>,
+    },
+    List(LoxList),
+    Map(LoxMap),
 }
 
-/*
-impl std::fmt::Display for LoxFunction {}
-*/
+impl fmt::Display for LoxObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Float(n) => write!(f, "{n}"),
+            Self::String(s) | Self::VarName(s) => write!(f, "{s}"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Nil => write!(f, "nil"),
+            // the same as the vm prints, the source is what `lox ast` is for
+            Self::Closure { declaration, .. } => write!(f, "<fn {}>", declaration.name),
+            Self::Native(native) => write!(f, "{native}"),
+            Self::VmClosure(closure) => write!(f, "{closure}"),
+            Self::Class(class) => write!(f, "{class}"),
+            Self::Instance(instance) => write!(f, "{instance}"),
+            Self::BoundMethod { method, .. } => write!(f, "{method}"),
+            Self::List(list) => write!(f, "{list}"),
+            Self::Map(map) => write!(f, "{map}"),
+        }
+    }
+}
 
 impl LoxObject {
     pub fn truthy(&self) -> bool {
         !matches!(self, Self::Bool(false) | Self::Nil)
     }
+
+    /// `object[index]`, lists take whole numbers and maps take any valid key
+    pub fn get_index(&self, index: &LoxObject) -> Result<LoxObject> {
+        match self {
+            Self::List(list) => list.get(index),
+            Self::Map(map) => map.get(index),
+            _ => Err(RuntimeError::NotIndexable),
+        }
+    }
+
+    /// `object[index] = value`, maps add the key if it's missing but lists don't grow
+    pub fn set_index(&self, index: &LoxObject, value: LoxObject) -> Result<()> {
+        match self {
+            Self::List(list) => list.set(index, value),
+            Self::Map(map) => map.set(index, value),
+            _ => Err(RuntimeError::NotIndexable),
+        }
+    }
 }
 
 // logic for evaluating is handled through trait implementations, returning Error for invalid type
@@ -116,12 +160,16 @@ impl ops::Div for LoxObject {
 
 impl cmp::PartialEq for LoxObject {
     fn eq(&self, other: &Self) -> bool {
-        use LoxObject::{Bool, Float, Nil, String};
+        use LoxObject::{Bool, Class, Float, Instance, List, Map, Nil, String};
         match (self, other) {
             (Nil, Nil) => true,
             (Bool(a), Bool(b)) => a == b,
             (String(a), String(b)) => a == b,
             (Float(a), Float(b)) => a == b,
+            (Class(a), Class(b)) => Rc::ptr_eq(a, b),
+            (Instance(a), Instance(b)) => a.same(b),
+            (List(a), List(b)) => a.same(b),
+            (Map(a), Map(b)) => a.same(b),
             (_, _) => false,
         }
     }
@@ -1,4 +1,8 @@
+pub mod export;
 pub mod expression;
 pub mod lox_callable;
+pub mod lox_class;
+pub mod lox_list;
+pub mod lox_map;
 pub mod lox_object;
 pub mod statement;
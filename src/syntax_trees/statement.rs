@@ -1,52 +1,130 @@
 use super::expression::Expression;
-use crate::scanner::ScannedToken;
+use crate::scanner::Span;
 use crate::token::Identifier;
 use crate::token::SmartString;
 use std::fmt;
+/// Like expressions, every variant knows the span of source it was parsed from
 #[derive(Clone, Debug)]
 pub enum Statement {
-    Expression(Expression),
-    Print(Expression),
+    Expression {
+        expression: Expression,
+        span: Span,
+    },
+    Print {
+        expression: Expression,
+        span: Span,
+    },
     Var {
         name: SmartString,
         initializer: Option<Expression>,
+        span: Span,
     },
+    /// increment is only set for desugared for loops, it runs after the body even when the
+    /// body hits a continue. for_loop marks the ones that were written with for, so the source
+    /// can be given back the way it was written
     While {
         condition: Expression,
         body: Box<Statement>,
+        increment: Option<Expression>,
+        for_loop: bool,
+        span: Span,
     },
     Function(Function),
+    Class {
+        name: Identifier,
+        superclass: Option<Expression>,
+        methods: Vec<Function>,
+        span: Span,
+    },
     If {
         condition: Expression,
         then: Box<Statement>,
         else_case: Option<Box<Statement>>,
+        span: Span,
+    },
+    Block {
+        statements: Vec<Statement>,
+        span: Span,
     },
-    Block(Vec<Statement>),
     Return {
-        token: ScannedToken,
         value: Option<Expression>,
+        span: Span,
+    },
+    Break {
+        span: Span,
+    },
+    Continue {
+        span: Span,
     },
 }
 
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Function(function) => function.span,
+            Self::Expression { span, .. }
+            | Self::Print { span, .. }
+            | Self::Var { span, .. }
+            | Self::While { span, .. }
+            | Self::Class { span, .. }
+            | Self::If { span, .. }
+            | Self::Block { span, .. }
+            | Self::Return { span, .. }
+            | Self::Break { span }
+            | Self::Continue { span } => *span,
+        }
+    }
+}
+
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Expression(e) => write!(f, "{}", e),
-            Self::Print(e) => write!(f, "print {}", e),
+            Self::Expression { expression, .. } => write!(f, "{}", expression),
+            Self::Print { expression, .. } => write!(f, "print {}", expression),
             Self::Var {
                 name,
                 initializer: Some(initializer),
+                ..
             } => write!(f, "var {name} = {initializer}"),
             Self::Var {
                 name,
                 initializer: None,
+                ..
             } => write!(f, "var {name}"),
-            Self::While { condition, body } => write!(f, "while {condition} {{{body}}}"),
+            Self::While {
+                condition,
+                body,
+                increment: Some(increment),
+                ..
+            } => write!(f, "while {condition} {{{body}}} then {increment}"),
+            Self::While {
+                condition,
+                body,
+                increment: None,
+                ..
+            } => write!(f, "while {condition} {{{body}}}"),
             Self::Function(fun) => write!(f, "{}", fun),
+            Self::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => {
+                write!(f, "class {name}")?;
+                if let Some(superclass) = superclass {
+                    write!(f, " < {superclass}")?;
+                }
+                write!(f, " {{")?;
+                for method in methods {
+                    writeln!(f, "{}", method)?;
+                }
+                write!(f, "}}")
+            }
             Self::If {
                 condition,
                 then,
                 else_case: Some(else_case),
+                ..
             } => {
                 write!(f, "if {condition} {{\n{then}\n}}else{{\n{else_case}\n}})")
             }
@@ -54,13 +132,16 @@ impl fmt::Display for Statement {
                 condition,
                 then,
                 else_case: None,
+                ..
             } => write!(f, "if {condition} {{\n{then}\n}}"),
-            Self::Block(block) => format_body(f, block),
+            Self::Block { statements, .. } => format_body(f, statements),
             Self::Return {
                 value: Some(expression),
                 ..
             } => write!(f, "return {expression};"),
             Self::Return { value: None, .. } => write!(f, "return;"),
+            Self::Break { .. } => write!(f, "break;"),
+            Self::Continue { .. } => write!(f, "continue;"),
         }
     }
 }
@@ -70,6 +151,7 @@ pub struct Function {
     pub name: Identifier,
     pub params: Vec<Identifier>,
     pub body: Vec<Statement>,
+    pub span: Span,
 }
 
 impl fmt::Display for Function {
@@ -11,7 +11,10 @@ pub enum Token {
     RIGHTPAREN,
     LEFTBRACE,
     RIGHTBRACE,
+    LEFTBRACKET,
+    RIGHTBRACKET,
     COMMA,
+    COLON,
     DOT,
     MINUS,
     PLUS,
@@ -33,7 +36,9 @@ pub enum Token {
     STRING(SmartString),
     NUMBER(f64),
     AND,
+    BREAK,
     CLASS,
+    CONTINUE,
     ELSE,
     FALSE,
     FUN,
@@ -155,7 +160,10 @@ impl TryFrom<char> for Token {
             ')' => Ok(Self::RIGHTPAREN),
             '{' => Ok(Self::LEFTBRACE),
             '}' => Ok(Self::RIGHTBRACE),
+            '[' => Ok(Self::LEFTBRACKET),
+            ']' => Ok(Self::RIGHTBRACKET),
             ',' => Ok(Self::COMMA),
+            ':' => Ok(Self::COLON),
             '.' => Ok(Self::DOT),
             '-' => Ok(Self::MINUS),
             '+' => Ok(Self::PLUS),
@@ -172,7 +180,9 @@ impl Token {
     pub fn from_keyword(keyword: &str) -> Option<Self> {
         match keyword {
             "and" => Some(Self::AND),
+            "break" => Some(Self::BREAK),
             "class" => Some(Self::CLASS),
+            "continue" => Some(Self::CONTINUE),
             "else" => Some(Self::ELSE),
             "false" => Some(Self::FALSE),
             "for" => Some(Self::FOR),
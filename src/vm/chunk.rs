@@ -0,0 +1,234 @@
+use crate::scanner::Span;
+use crate::syntax_trees::lox_object::LoxObject;
+use std::fmt;
+use std::rc::Rc;
+
+type Result<T> = std::result::Result<T, fmt::Error>;
+
+use super::object::Function;
+
+/// Instructions are a single byte, followed by their operands. Constant, global and property
+/// names are u16 indices into the constant pool, jumps are u16 offsets, and local/upvalue slots
+/// are a single byte
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OpCode {
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal,
+    SetLocal,
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    GetUpvalue,
+    SetUpvalue,
+    GetProperty,
+    SetProperty,
+    GetSuper,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    Jump,
+    JumpIfFalse,
+    Loop,
+    Call,
+    /// function index, then an (is_local, index) byte pair per upvalue
+    Closure,
+    CloseUpvalue,
+    Return,
+    /// name constant, method count, and whether a superclass sits under the methods
+    Class,
+    /// u16 element count, the elements are on the stack in order
+    BuildList,
+    /// u16 entry count, each key sits under its value on the stack
+    BuildMap,
+    Index,
+    SetIndex,
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> std::result::Result<Self, u8> {
+        use OpCode::*;
+        const OPS: [OpCode; 40] = [
+            Constant,
+            Nil,
+            True,
+            False,
+            Pop,
+            GetLocal,
+            SetLocal,
+            GetGlobal,
+            DefineGlobal,
+            SetGlobal,
+            GetUpvalue,
+            SetUpvalue,
+            GetProperty,
+            SetProperty,
+            GetSuper,
+            Equal,
+            NotEqual,
+            Greater,
+            GreaterEqual,
+            Less,
+            LessEqual,
+            Add,
+            Subtract,
+            Multiply,
+            Divide,
+            Not,
+            Negate,
+            Print,
+            Jump,
+            JumpIfFalse,
+            Loop,
+            Call,
+            Closure,
+            CloseUpvalue,
+            Return,
+            Class,
+            BuildList,
+            BuildMap,
+            Index,
+            SetIndex,
+        ];
+        OPS.get(byte as usize).copied().ok_or(byte)
+    }
+}
+
+/// A compiled function body. Where each byte came from in the source is kept run-length encoded,
+/// one entry for every run of bytes sharing a span, holding the offset the run starts at
+#[derive(Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    spans: Vec<(usize, Span)>,
+    pub constants: Vec<LoxObject>,
+    pub functions: Vec<Rc<Function>>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, span: Span) {
+        if self.spans.last().is_none_or(|&(_, last)| last != span) {
+            self.spans.push((self.code.len(), span));
+        }
+        self.code.push(byte);
+    }
+
+    /// The span of the byte at `offset`
+    pub fn span(&self, offset: usize) -> Span {
+        let run = self.spans.partition_point(|&(start, _)| start <= offset);
+        self.spans[run - 1].1
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    fn disassemble_instruction(&self, f: &mut fmt::Formatter, offset: usize) -> Result<usize> {
+        let Ok(op) = OpCode::try_from(self.code[offset]) else {
+            writeln!(f, "{offset:04} unknown opcode {}", self.code[offset])?;
+            return Ok(offset + 1);
+        };
+        write!(f, "{offset:04} {:4} {op:?}", self.span(offset).line)?;
+        let next = match op {
+            OpCode::Constant
+            | OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetProperty
+            | OpCode::SetProperty
+            | OpCode::GetSuper => {
+                let index = self.read_u16(offset + 1) as usize;
+                write!(f, " {}", self.constants[index])?;
+                offset + 3
+            }
+            OpCode::GetLocal
+            | OpCode::SetLocal
+            | OpCode::GetUpvalue
+            | OpCode::SetUpvalue
+            | OpCode::Call => {
+                write!(f, " {}", self.code[offset + 1])?;
+                offset + 2
+            }
+            OpCode::Jump | OpCode::JumpIfFalse => {
+                write!(f, " -> {}", offset + 3 + self.read_u16(offset + 1) as usize)?;
+                offset + 3
+            }
+            OpCode::Loop => {
+                write!(f, " -> {}", offset + 3 - self.read_u16(offset + 1) as usize)?;
+                offset + 3
+            }
+            OpCode::Closure => {
+                let function = &self.functions[self.read_u16(offset + 1) as usize];
+                write!(f, " {}", function.name)?;
+                offset + 3 + 2 * function.upvalue_count
+            }
+            OpCode::BuildList | OpCode::BuildMap => {
+                write!(f, " {}", self.read_u16(offset + 1))?;
+                offset + 3
+            }
+            OpCode::Class => {
+                let index = self.read_u16(offset + 1) as usize;
+                write!(f, " {} {}", self.constants[index], self.code[offset + 3])?;
+                offset + 5
+            }
+            _ => offset + 1,
+        };
+        writeln!(f)?;
+        Ok(next)
+    }
+}
+
+impl fmt::Display for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut offset = 0;
+        while offset < self.code.len() {
+            offset = self.disassemble_instruction(f, offset)?;
+        }
+        for function in &self.functions {
+            writeln!(f, "== {} ==", function.name)?;
+            write!(f, "{}", function.chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opcode_round_trip() {
+        for byte in 0..=OpCode::SetIndex as u8 {
+            assert_eq!(OpCode::try_from(byte).map(|op| op as u8), Ok(byte));
+        }
+        assert!(OpCode::try_from(OpCode::SetIndex as u8 + 1).is_err());
+    }
+
+    #[test]
+    fn span_runs() {
+        let first = Span::new(1, 0, 0, 1);
+        let second = Span::new(2, 0, 2, 3);
+        let mut chunk = Chunk::default();
+        for span in [first, first, first, second, second, first] {
+            chunk.write(OpCode::Nil as u8, span);
+        }
+        assert_eq!(chunk.spans.len(), 3);
+        let spans: Vec<_> = (0..chunk.code.len()).map(|i| chunk.span(i)).collect();
+        assert_eq!(spans, [first, first, first, second, second, first]);
+    }
+}
@@ -0,0 +1,713 @@
+use super::chunk::{Chunk, OpCode};
+use super::error::CompileError;
+use super::object::Function;
+use crate::scanner::Span;
+use crate::syntax_trees::expression::{BinaryOperator, Expression, LogicalOperator, UnaryOperator};
+use crate::syntax_trees::lox_object::LoxObject;
+use crate::syntax_trees::statement::{self, Statement};
+use crate::token::SmartString;
+use std::rc::Rc;
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+/// Compiles a whole program into the body of an implicit top level function
+pub fn compile(statements: &[Statement]) -> Result<Rc<Function>> {
+    let mut compiler = Compiler {
+        states: vec![FunctionState::new("".into(), FunctionType::Script)],
+        span: Span::default(),
+    };
+    for statement in statements {
+        compiler.statement(statement)?;
+    }
+    compiler.emit_return();
+    let script = compiler
+        .states
+        .pop()
+        .expect("script state is never popped early");
+    Ok(Rc::new(script.finish()))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum FunctionType {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+struct Local {
+    name: SmartString,
+    depth: usize,
+    captured: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+// forward jumps out of a loop body, patched once we know where they land
+struct Loop {
+    scope_depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+// one per function being compiled, nested function declarations push a new one
+struct FunctionState {
+    name: SmartString,
+    kind: FunctionType,
+    arity: usize,
+    chunk: Chunk,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+}
+
+impl FunctionState {
+    fn new(name: SmartString, kind: FunctionType) -> Self {
+        // slot zero holds the callee, or the receiver for methods
+        let slot_zero = match kind {
+            FunctionType::Method | FunctionType::Initializer => "this",
+            FunctionType::Script | FunctionType::Function => "",
+        };
+        Self {
+            name,
+            kind,
+            arity: 0,
+            chunk: Chunk::default(),
+            locals: vec![Local {
+                name: slot_zero.into(),
+                depth: 0,
+                captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+        }
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|local| local.name == name)
+    }
+
+    fn finish(self) -> Function {
+        Function {
+            name: self.name,
+            arity: self.arity,
+            upvalue_count: self.upvalues.len(),
+            chunk: self.chunk,
+        }
+    }
+}
+
+struct Compiler {
+    states: Vec<FunctionState>,
+    // bytes get the span of the node that emitted them, anything emitted while setting up a node
+    // inherits the last one we saw
+    span: Span,
+}
+
+impl Compiler {
+    // errors come back located at the innermost statement or expression they happened in
+    fn statement(&mut self, statement: &Statement) -> Result<()> {
+        self.compile_statement(statement)
+            .map_err(|e| e.error(statement.span()))
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Expression { expression, .. } => {
+                self.expression(expression)?;
+                self.emit_op(OpCode::Pop);
+            }
+            Statement::Print { expression, .. } => {
+                self.expression(expression)?;
+                self.emit_op(OpCode::Print);
+            }
+            Statement::Var {
+                name, initializer, ..
+            } => {
+                match initializer {
+                    Some(initializer) => self.expression(initializer)?,
+                    None => self.emit_op(OpCode::Nil),
+                }
+                self.define_variable(name)?;
+            }
+            Statement::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => {
+                let loop_start = self.chunk().code.len();
+                self.expression(condition)?;
+                let exit = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop);
+                let scope_depth = self.state().scope_depth;
+                self.state().loops.push(Loop {
+                    scope_depth,
+                    breaks: Vec::new(),
+                    continues: Vec::new(),
+                });
+                self.statement(body)?;
+                let Loop {
+                    breaks, continues, ..
+                } = self.state().loops.pop().expect("pushed above");
+                for jump in continues {
+                    self.patch_jump(jump)?;
+                }
+                if let Some(increment) = increment {
+                    self.expression(increment)?;
+                    self.emit_op(OpCode::Pop);
+                }
+                self.emit_loop(loop_start)?;
+                self.patch_jump(exit)?;
+                self.emit_op(OpCode::Pop);
+                // the condition was already popped on the way into the body
+                for jump in breaks {
+                    self.patch_jump(jump)?;
+                }
+            }
+            Statement::Function(function) => {
+                let name = SmartString::from(function.name.as_ref());
+                // declared before the body so local functions can call themselves
+                if self.state().scope_depth > 0 {
+                    self.add_local(name)?;
+                    self.function(function, FunctionType::Function)?;
+                } else {
+                    self.function(function, FunctionType::Function)?;
+                    self.define_variable(&name)?;
+                }
+            }
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => self.class(name.as_ref(), superclass.as_ref(), methods)?,
+            Statement::If {
+                condition,
+                then,
+                else_case,
+                ..
+            } => {
+                self.expression(condition)?;
+                let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop);
+                self.statement(then)?;
+                let else_jump = self.emit_jump(OpCode::Jump);
+                self.patch_jump(then_jump)?;
+                self.emit_op(OpCode::Pop);
+                if let Some(else_case) = else_case {
+                    self.statement(else_case)?;
+                }
+                self.patch_jump(else_jump)?;
+            }
+            Statement::Block { statements, .. } => {
+                self.begin_scope();
+                for statement in statements {
+                    self.statement(statement)?;
+                }
+                self.end_scope();
+            }
+            Statement::Return { value, span } => {
+                self.span = *span;
+                match value {
+                    Some(value) => {
+                        self.expression(value)?;
+                        self.emit_op(OpCode::Return);
+                    }
+                    None => self.emit_return(),
+                }
+            }
+            Statement::Break { span } => {
+                self.span = *span;
+                let jump = self.loop_jump();
+                self.current_loop().breaks.push(jump);
+            }
+            Statement::Continue { span } => {
+                self.span = *span;
+                let jump = self.loop_jump();
+                self.current_loop().continues.push(jump);
+            }
+        }
+        Ok(())
+    }
+
+    fn current_loop(&mut self) -> &mut Loop {
+        self.state()
+            .loops
+            .last_mut()
+            .expect("the parser only allows break and continue inside loops")
+    }
+
+    // discards the locals declared inside the loop body without forgetting them, the code after
+    // the jump still sees them in scope
+    fn loop_jump(&mut self) -> usize {
+        let depth = self.current_loop().scope_depth;
+        let captured: Vec<bool> = self
+            .state()
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth > depth)
+            .map(|local| local.captured)
+            .collect();
+        for captured in captured {
+            self.emit_op(if captured {
+                OpCode::CloseUpvalue
+            } else {
+                OpCode::Pop
+            });
+        }
+        self.emit_jump(OpCode::Jump)
+    }
+
+    fn class(
+        &mut self,
+        name: &str,
+        superclass: Option<&Expression>,
+        methods: &[statement::Function],
+    ) -> Result<()> {
+        let name = SmartString::from(name);
+        // the class is built in one go after its methods, so locals get a placeholder slot that
+        // methods can already capture
+        let local = self.state().scope_depth > 0;
+        if local {
+            self.emit_op(OpCode::Nil);
+            self.add_local(name.clone())?;
+        }
+
+        if let Some(superclass) = superclass {
+            self.begin_scope();
+            self.expression(superclass)?;
+            self.add_local("super".into())?;
+            self.get_variable("super")?;
+        }
+
+        for method in methods {
+            let kind = if method.name.as_ref() == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.function(method, kind)?;
+        }
+
+        let method_count = u8::try_from(methods.len()).map_err(|_| CompileError::TooManyMethods)?;
+        let name_constant = self.identifier(&name)?;
+        self.emit_op(OpCode::Class);
+        self.emit_u16(name_constant);
+        self.emit_byte(method_count);
+        self.emit_byte(superclass.is_some().into());
+
+        if local {
+            let slot = self
+                .state()
+                .resolve_local(&name)
+                .expect("placeholder was added");
+            self.emit_op(OpCode::SetLocal);
+            self.emit_byte(slot as u8);
+            self.emit_op(OpCode::Pop);
+        } else {
+            // can't go through define_variable, we might be inside the super scope
+            self.emit_op(OpCode::DefineGlobal);
+            self.emit_u16(name_constant);
+        }
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, function: &statement::Function, kind: FunctionType) -> Result<()> {
+        self.states
+            .push(FunctionState::new(function.name.as_ref().into(), kind));
+        self.begin_scope();
+        for param in &function.params {
+            self.state().arity += 1;
+            self.add_local(param.as_ref().into())?;
+        }
+        for statement in &function.body {
+            self.statement(statement)?;
+        }
+        self.emit_return();
+
+        let state = self.states.pop().expect("pushed above");
+        let upvalues = state.upvalues.clone();
+        let chunk = self.chunk();
+        let index =
+            u16::try_from(chunk.functions.len()).map_err(|_| CompileError::TooManyConstants)?;
+        chunk.functions.push(Rc::new(state.finish()));
+
+        self.emit_op(OpCode::Closure);
+        self.emit_u16(index);
+        for upvalue in upvalues {
+            self.emit_byte(upvalue.is_local.into());
+            self.emit_byte(upvalue.index);
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<()> {
+        self.compile_expression(expression)
+            .map_err(|e| e.error(expression.span()))
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) -> Result<()> {
+        match expression {
+            Expression::Literal {
+                value: LoxObject::Nil,
+                ..
+            } => self.emit_op(OpCode::Nil),
+            Expression::Literal {
+                value: LoxObject::Bool(true),
+                ..
+            } => self.emit_op(OpCode::True),
+            Expression::Literal {
+                value: LoxObject::Bool(false),
+                ..
+            } => self.emit_op(OpCode::False),
+            Expression::Literal { value, .. } => {
+                let constant = self.make_constant(value.clone())?;
+                self.emit_op(OpCode::Constant);
+                self.emit_u16(constant);
+            }
+            Expression::Grouping { inner, .. } => self.expression(inner)?,
+            Expression::Unary {
+                operator,
+                inner,
+                span,
+            } => {
+                self.expression(inner)?;
+                self.span = *span;
+                self.emit_op(match operator {
+                    UnaryOperator::BANG => OpCode::Not,
+                    UnaryOperator::MINUS => OpCode::Negate,
+                });
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                span,
+            } => {
+                self.expression(left)?;
+                self.expression(right)?;
+                self.span = *span;
+                self.emit_op(match operator {
+                    BinaryOperator::EQUALEQUAL => OpCode::Equal,
+                    BinaryOperator::BANGEQUAL => OpCode::NotEqual,
+                    BinaryOperator::GREATER => OpCode::Greater,
+                    BinaryOperator::GREATEREQUAL => OpCode::GreaterEqual,
+                    BinaryOperator::LESS => OpCode::Less,
+                    BinaryOperator::LESSEQUAL => OpCode::LessEqual,
+                    BinaryOperator::PLUS => OpCode::Add,
+                    BinaryOperator::MINUS => OpCode::Subtract,
+                    BinaryOperator::STAR => OpCode::Multiply,
+                    BinaryOperator::SLASH => OpCode::Divide,
+                });
+            }
+            Expression::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                self.expression(left)?;
+                if *operator == LogicalOperator::AND {
+                    let end = self.emit_jump(OpCode::JumpIfFalse);
+                    self.emit_op(OpCode::Pop);
+                    self.expression(right)?;
+                    self.patch_jump(end)?;
+                } else {
+                    let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+                    let end = self.emit_jump(OpCode::Jump);
+                    self.patch_jump(else_jump)?;
+                    self.emit_op(OpCode::Pop);
+                    self.expression(right)?;
+                    self.patch_jump(end)?;
+                }
+            }
+            Expression::Variable { name, span, .. } => {
+                self.span = *span;
+                self.get_variable(name)?;
+            }
+            Expression::Assign {
+                name, value, span, ..
+            } => {
+                self.expression(value)?;
+                self.span = *span;
+                self.set_variable(name)?;
+            }
+            Expression::Call { callee, args, span } => {
+                self.expression(callee)?;
+                for arg in args {
+                    self.expression(arg)?;
+                }
+                self.span = *span;
+                let count = u8::try_from(args.len()).map_err(|_| CompileError::TooManyArgs)?;
+                self.emit_op(OpCode::Call);
+                self.emit_byte(count);
+            }
+            Expression::Get { object, name, span } => {
+                self.expression(object)?;
+                self.span = *span;
+                let name = self.identifier(name)?;
+                self.emit_op(OpCode::GetProperty);
+                self.emit_u16(name);
+            }
+            Expression::Set {
+                object,
+                name,
+                value,
+                span,
+            } => {
+                self.expression(object)?;
+                self.expression(value)?;
+                self.span = *span;
+                let name = self.identifier(name)?;
+                self.emit_op(OpCode::SetProperty);
+                self.emit_u16(name);
+            }
+            Expression::List { elements, .. } => {
+                for element in elements {
+                    self.expression(element)?;
+                }
+                let count =
+                    u16::try_from(elements.len()).map_err(|_| CompileError::TooManyElements)?;
+                self.emit_op(OpCode::BuildList);
+                self.emit_u16(count);
+            }
+            Expression::Map { entries, span } => {
+                for (key, value) in entries {
+                    self.expression(key)?;
+                    self.expression(value)?;
+                }
+                let count =
+                    u16::try_from(entries.len()).map_err(|_| CompileError::TooManyElements)?;
+                self.span = *span;
+                self.emit_op(OpCode::BuildMap);
+                self.emit_u16(count);
+            }
+            Expression::Index {
+                object,
+                index,
+                span,
+            } => {
+                self.expression(object)?;
+                self.expression(index)?;
+                self.span = *span;
+                self.emit_op(OpCode::Index);
+            }
+            Expression::SetIndex {
+                object,
+                index,
+                value,
+                span,
+            } => {
+                self.expression(object)?;
+                self.expression(index)?;
+                self.expression(value)?;
+                self.span = *span;
+                self.emit_op(OpCode::SetIndex);
+            }
+            Expression::This { span, .. } => {
+                self.span = *span;
+                self.get_variable("this")?;
+            }
+            Expression::Super { method, span, .. } => {
+                self.span = *span;
+                self.get_variable("this")?;
+                self.get_variable("super")?;
+                let method = self.identifier(method)?;
+                self.emit_op(OpCode::GetSuper);
+                self.emit_u16(method);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_variable(&mut self, name: &str) -> Result<()> {
+        let current = self.states.len() - 1;
+        if let Some(slot) = self.state().resolve_local(name) {
+            self.emit_op(OpCode::GetLocal);
+            self.emit_byte(slot as u8);
+        } else if let Some(index) = self.resolve_upvalue(current, name)? {
+            self.emit_op(OpCode::GetUpvalue);
+            self.emit_byte(index);
+        } else {
+            let name = self.identifier(name)?;
+            self.emit_op(OpCode::GetGlobal);
+            self.emit_u16(name);
+        }
+        Ok(())
+    }
+
+    fn set_variable(&mut self, name: &str) -> Result<()> {
+        let current = self.states.len() - 1;
+        if let Some(slot) = self.state().resolve_local(name) {
+            self.emit_op(OpCode::SetLocal);
+            self.emit_byte(slot as u8);
+        } else if let Some(index) = self.resolve_upvalue(current, name)? {
+            self.emit_op(OpCode::SetUpvalue);
+            self.emit_byte(index);
+        } else {
+            let name = self.identifier(name)?;
+            self.emit_op(OpCode::SetGlobal);
+            self.emit_u16(name);
+        }
+        Ok(())
+    }
+
+    // walks out through the enclosing functions, threading the variable through each of their
+    // upvalue lists on the way back in
+    fn resolve_upvalue(&mut self, state: usize, name: &str) -> Result<Option<u8>> {
+        if state == 0 {
+            return Ok(None);
+        }
+        let enclosing = state - 1;
+        if let Some(slot) = self.states[enclosing].resolve_local(name) {
+            self.states[enclosing].locals[slot].captured = true;
+            return self.add_upvalue(state, slot as u8, true).map(Some);
+        }
+        match self.resolve_upvalue(enclosing, name)? {
+            Some(index) => self.add_upvalue(state, index, false).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn add_upvalue(&mut self, state: usize, index: u8, is_local: bool) -> Result<u8> {
+        let upvalue = UpvalueRef { index, is_local };
+        let upvalues = &mut self.states[state].upvalues;
+        if let Some(existing) = upvalues.iter().position(|x| *x == upvalue) {
+            return Ok(existing as u8);
+        }
+        let index = u8::try_from(upvalues.len()).map_err(|_| CompileError::TooManyUpvalues)?;
+        upvalues.push(upvalue);
+        Ok(index)
+    }
+
+    fn define_variable(&mut self, name: &SmartString) -> Result<()> {
+        if self.state().scope_depth > 0 {
+            self.add_local(name.clone())
+        } else {
+            let name = self.identifier(name)?;
+            self.emit_op(OpCode::DefineGlobal);
+            self.emit_u16(name);
+            Ok(())
+        }
+    }
+
+    fn add_local(&mut self, name: SmartString) -> Result<()> {
+        let state = self.state();
+        if state.locals.len() > u8::MAX as usize {
+            return Err(CompileError::TooManyLocals);
+        }
+        let depth = state.scope_depth;
+        state.locals.push(Local {
+            name,
+            depth,
+            captured: false,
+        });
+        Ok(())
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        let state = self.state();
+        state.scope_depth -= 1;
+        let depth = state.scope_depth;
+        while let Some(local) = self.state().locals.pop_if(|local| local.depth > depth) {
+            if local.captured {
+                self.emit_op(OpCode::CloseUpvalue);
+            } else {
+                self.emit_op(OpCode::Pop);
+            }
+        }
+    }
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.states
+            .last_mut()
+            .expect("there's always a script state")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().chunk
+    }
+
+    fn identifier(&mut self, name: &str) -> Result<u16> {
+        let existing = self
+            .chunk()
+            .constants
+            .iter()
+            .position(|constant| matches!(constant, LoxObject::String(s) if s == name));
+        match existing {
+            Some(index) => Ok(index as u16),
+            None => self.make_constant(LoxObject::String(name.into())),
+        }
+    }
+
+    fn make_constant(&mut self, value: LoxObject) -> Result<u16> {
+        let constants = &mut self.chunk().constants;
+        let index = u16::try_from(constants.len()).map_err(|_| CompileError::TooManyConstants)?;
+        constants.push(value);
+        Ok(index)
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        let span = self.span;
+        self.chunk().write(byte, span);
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        self.emit_byte(op as u8);
+    }
+
+    fn emit_u16(&mut self, value: u16) {
+        let [high, low] = value.to_be_bytes();
+        self.emit_byte(high);
+        self.emit_byte(low);
+    }
+
+    fn emit_return(&mut self) {
+        if self.state().kind == FunctionType::Initializer {
+            self.emit_op(OpCode::GetLocal);
+            self.emit_byte(0);
+        } else {
+            self.emit_op(OpCode::Nil);
+        }
+        self.emit_op(OpCode::Return);
+    }
+
+    /// Emits a jump with a placeholder offset, returning where to patch it
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op(op);
+        self.emit_u16(u16::MAX);
+        self.chunk().code.len() - 2
+    }
+
+    fn patch_jump(&mut self, offset: usize) -> Result<()> {
+        let code = &mut self.chunk().code;
+        let jump =
+            u16::try_from(code.len() - offset - 2).map_err(|_| CompileError::JumpTooLarge)?;
+        code[offset..offset + 2].copy_from_slice(&jump.to_be_bytes());
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) -> Result<()> {
+        self.emit_op(OpCode::Loop);
+        let offset = self.chunk().code.len() - loop_start + 2;
+        let offset = u16::try_from(offset).map_err(|_| CompileError::LoopTooLarge)?;
+        self.emit_u16(offset);
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::scanner::Span;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum CompileError {
+    #[error("Too many constants in one chunk.")]
+    TooManyConstants,
+    #[error("Too many local variables in function.")]
+    TooManyLocals,
+    #[error("Too many closure variables in function.")]
+    TooManyUpvalues,
+    #[error("Too much code to jump over.")]
+    JumpTooLarge,
+    #[error("Loop body too large.")]
+    LoopTooLarge,
+    #[error("Can't have more than 255 Arguments")]
+    TooManyArgs,
+    #[error("Too many methods in one class.")]
+    TooManyMethods,
+    #[error("Too many elements in one list or map literal.")]
+    TooManyElements,
+
+    /// Any of the above, along with where it happened
+    #[error("{error}")]
+    At {
+        error: Box<CompileError>,
+        span: Span,
+    },
+}
+
+impl CompileError {
+    /// Compile errors are numbered E04xx
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::At { error, .. } => error.code(),
+            Self::TooManyConstants => "E0401",
+            Self::TooManyLocals => "E0402",
+            Self::TooManyUpvalues => "E0403",
+            Self::JumpTooLarge => "E0404",
+            Self::LoopTooLarge => "E0405",
+            Self::TooManyArgs => "E0406",
+            Self::TooManyMethods => "E0407",
+            Self::TooManyElements => "E0408",
+        }
+    }
+
+    /// Attaches where the error happened, errors that already know keep their span
+    pub fn error(self, span: Span) -> Self {
+        match self {
+            located @ Self::At { .. } => located,
+            error => Self::At {
+                error: Box::new(error),
+                span,
+            },
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::At { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// What went wrong, without the location
+    pub fn kind(&self) -> &Self {
+        match self {
+            Self::At { error, .. } => error.kind(),
+            error => error,
+        }
+    }
+}
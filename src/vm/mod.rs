@@ -0,0 +1,620 @@
+pub mod chunk;
+mod compiler;
+pub mod error;
+pub mod object;
+
+pub use compiler::compile;
+pub use error::CompileError;
+
+use crate::error::Error;
+use crate::interpreter::{self, RuntimeError, TraceFrame};
+use crate::parser;
+use crate::resolver;
+use crate::syntax_trees::expression::Expression;
+use crate::syntax_trees::lox_callable::LoxCallable;
+use crate::syntax_trees::lox_class::{LoxClass, LoxInstance};
+use crate::syntax_trees::lox_list::LoxList;
+use crate::syntax_trees::lox_map::LoxMap;
+use crate::syntax_trees::lox_object::LoxObject;
+use crate::syntax_trees::statement::Statement;
+use crate::token::SmartString;
+use chunk::OpCode;
+use object::{Closure, Function, Upvalue};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// How deep calls can nest, the tree-walker holds itself to the same limit
+pub(crate) const FRAMES_MAX: usize = 1024;
+
+struct CallFrame {
+    closure: Closure,
+    ip: usize,
+    // stack index of the callee, arguments and locals sit right above it
+    base: usize,
+}
+
+/// Stack based backend, runs the same programs as the tree-walker after compiling them to
+/// bytecode. Globals persist between calls to [`Vm::eval`]
+pub struct Vm {
+    stack: Vec<LoxObject>,
+    frames: Vec<CallFrame>,
+    globals: HashMap<SmartString, LoxObject>,
+    // sorted by stack slot
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        let globals = interpreter::natives::natives()
+            .into_iter()
+            .map(|native| (native.name().into(), LoxObject::Native(native)))
+            .collect();
+        Self {
+            stack: Vec::with_capacity(256),
+            frames: Vec::with_capacity(FRAMES_MAX),
+            globals,
+            open_upvalues: Vec::new(),
+        }
+    }
+
+    pub fn eval(&mut self, source: &str) -> std::result::Result<(), Error> {
+        let mut statements = parser::parse(source)?;
+        resolver::resolve(&mut statements)?;
+        let script = compile(&statements)?;
+        self.interpret(script)?;
+        Ok(())
+    }
+
+    pub fn define_global(&mut self, name: &str, value: LoxObject) {
+        self.globals.insert(name.into(), value);
+    }
+
+    /// Every global sorted by name
+    pub fn globals(&self) -> Vec<(SmartString, LoxObject)> {
+        let mut globals: Vec<_> = self
+            .globals
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        globals.sort_by(|(a, _), (b, _)| a.cmp(b));
+        globals
+    }
+
+    /// Runs a single expression and hands back its value, for the REPL to echo
+    pub fn evaluate(
+        &mut self,
+        mut expression: Expression,
+    ) -> std::result::Result<LoxObject, Error> {
+        resolver::resolve_expression(&mut expression)?;
+        // the resolver won't allow a top level return, but the compiler doesn't mind
+        let statement = Statement::Return {
+            span: expression.span(),
+            value: Some(expression),
+        };
+        let script = compile(&[statement])?;
+        Ok(self.interpret(script)?)
+    }
+
+    /// Gives back whatever the script returned, which is nil unless it came from [`Vm::evaluate`]
+    pub fn interpret(&mut self, script: Rc<Function>) -> Result<LoxObject> {
+        let closure = Closure {
+            function: script,
+            upvalues: Vec::new(),
+        };
+        self.stack.push(LoxObject::VmClosure(closure.clone()));
+        let result = self.call(closure, 0).and_then(|()| self.run());
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+        }
+        result
+    }
+
+    fn run(&mut self) -> Result<LoxObject> {
+        loop {
+            let op = self.read_byte();
+            let op = OpCode::try_from(op).expect("the compiler only emits valid opcodes");
+            match op {
+                OpCode::Constant => {
+                    let constant = self.read_constant().clone();
+                    self.push(constant);
+                }
+                OpCode::Nil => self.push(LoxObject::Nil),
+                OpCode::True => self.push(LoxObject::Bool(true)),
+                OpCode::False => self.push(LoxObject::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = self.frame().base + self.read_byte() as usize;
+                    self.push(self.stack[slot].clone());
+                }
+                OpCode::SetLocal => {
+                    let slot = self.frame().base + self.read_byte() as usize;
+                    self.stack[slot] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal => {
+                    let name = self.read_string();
+                    match self.globals.get(&name) {
+                        Some(value) => self.push(value.clone()),
+                        None => return Err(self.error(RuntimeError::Undefined(name))),
+                    }
+                }
+                OpCode::DefineGlobal => {
+                    let name = self.read_string();
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                }
+                OpCode::SetGlobal => {
+                    let name = self.read_string();
+                    let value = self.peek(0).clone();
+                    match self.globals.get_mut(&name) {
+                        Some(variable) => *variable = value,
+                        None => return Err(self.error(RuntimeError::Undefined(name))),
+                    }
+                }
+                OpCode::GetUpvalue => {
+                    let slot = self.read_byte() as usize;
+                    let value = match &*self.frame().closure.upvalues[slot].borrow() {
+                        Upvalue::Open(index) => self.stack[*index].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let slot = self.read_byte() as usize;
+                    let value = self.peek(0).clone();
+                    let upvalue = self.frame().closure.upvalues[slot].clone();
+                    let mut upvalue = upvalue.borrow_mut();
+                    match &mut *upvalue {
+                        Upvalue::Open(index) => self.stack[*index] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                OpCode::GetProperty => {
+                    let name = self.read_string();
+                    let LoxObject::Instance(instance) = self.pop() else {
+                        return Err(self.error(RuntimeError::NotInstance));
+                    };
+                    let value = instance.get(&name).map_err(|e| self.error(e))?;
+                    self.push(value);
+                }
+                OpCode::SetProperty => {
+                    let name = self.read_string();
+                    let value = self.pop();
+                    let LoxObject::Instance(instance) = self.pop() else {
+                        return Err(self.error(RuntimeError::NotInstance));
+                    };
+                    instance.set(name, value.clone());
+                    self.push(value);
+                }
+                OpCode::GetSuper => {
+                    let name = self.read_string();
+                    let (LoxObject::Class(superclass), LoxObject::Instance(this)) =
+                        (self.pop(), self.pop())
+                    else {
+                        unreachable!("super and this are always a class and an instance");
+                    };
+                    match superclass.bind(&name, &this) {
+                        Some(method) => self.push(method),
+                        None => return Err(self.error(RuntimeError::UndefinedProperty(name))),
+                    }
+                }
+                OpCode::BuildList => {
+                    let count = self.read_u16() as usize;
+                    let elements = self.stack.split_off(self.stack.len() - count);
+                    self.push(LoxObject::List(LoxList::new(elements)));
+                }
+                OpCode::BuildMap => {
+                    let count = self.read_u16() as usize;
+                    let mut flat = self
+                        .stack
+                        .split_off(self.stack.len() - 2 * count)
+                        .into_iter();
+                    let entries =
+                        std::iter::from_fn(|| Some((flat.next()?, flat.next()?))).collect();
+                    let map = LoxMap::new(entries).map_err(|e| self.error(e))?;
+                    self.push(LoxObject::Map(map));
+                }
+                OpCode::Index => {
+                    let index = self.pop();
+                    let value = self.pop().get_index(&index).map_err(|e| self.error(e))?;
+                    self.push(value);
+                }
+                OpCode::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    self.pop()
+                        .set_index(&index, value.clone())
+                        .map_err(|e| self.error(e))?;
+                    self.push(value);
+                }
+                OpCode::Equal => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.push(LoxObject::Bool(left == right));
+                }
+                OpCode::NotEqual => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.push(LoxObject::Bool(left != right));
+                }
+                OpCode::Greater => self.compare(|left, right| left > right)?,
+                OpCode::GreaterEqual => self.compare(|left, right| left >= right)?,
+                OpCode::Less => self.compare(|left, right| left < right)?,
+                OpCode::LessEqual => self.compare(|left, right| left <= right)?,
+                OpCode::Add => self.binary(|left, right| left + right)?,
+                OpCode::Subtract => self.binary(|left, right| left - right)?,
+                OpCode::Multiply => self.binary(|left, right| left * right)?,
+                OpCode::Divide => self.binary(|left, right| left / right)?,
+                OpCode::Not => {
+                    let value = !self.pop();
+                    let value = value.map_err(|e| self.error(e))?;
+                    self.push(value);
+                }
+                OpCode::Negate => {
+                    let value = -self.pop();
+                    let value = value.map_err(|e| self.error(e))?;
+                    self.push(value);
+                }
+                OpCode::Print => println!("{}", self.pop()),
+                OpCode::Jump => {
+                    let offset = self.read_u16();
+                    self.frame_mut().ip += offset as usize;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = self.read_u16();
+                    if !self.peek(0).truthy() {
+                        self.frame_mut().ip += offset as usize;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = self.read_u16();
+                    self.frame_mut().ip -= offset as usize;
+                }
+                OpCode::Call => {
+                    let count = self.read_byte() as usize;
+                    let callee = self.peek(count).clone();
+                    self.call_value(callee, count)?;
+                }
+                OpCode::Closure => self.closure(),
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("returning from a frame");
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+                    if self.frames.is_empty() {
+                        return Ok(result);
+                    }
+                    self.push(result);
+                }
+                OpCode::Class => self.class()?,
+            }
+        }
+    }
+
+    fn call_value(&mut self, callee: LoxObject, count: usize) -> Result<()> {
+        let base = self.stack.len() - count - 1;
+        match callee {
+            LoxObject::VmClosure(closure) => self.call(closure, count),
+            LoxObject::Native(native) => {
+                self.check_arity(native.arity(), count)?;
+                let args = self.stack.split_off(base + 1);
+                self.pop();
+                let result = native.call(args).map_err(|e| self.error(e))?;
+                self.push(result);
+                Ok(())
+            }
+            LoxObject::Class(class) => {
+                self.check_arity(class.arity(), count)?;
+                self.stack[base] = LoxObject::Instance(LoxInstance::new(class.clone()));
+                match class.find_method("init") {
+                    Some((LoxObject::VmClosure(initializer), _)) => {
+                        self.call(initializer.clone(), count)
+                    }
+                    _ => Ok(()),
+                }
+            }
+            LoxObject::BoundMethod {
+                receiver,
+                method: box_method,
+                ..
+            } => {
+                let LoxObject::VmClosure(method) = *box_method else {
+                    return Err(self.error(RuntimeError::NotCallable));
+                };
+                self.stack[base] = LoxObject::Instance(receiver);
+                self.call(method, count)
+            }
+            _ => Err(self.error(RuntimeError::NotCallable)),
+        }
+    }
+
+    fn call(&mut self, closure: Closure, count: usize) -> Result<()> {
+        self.check_arity(closure.function.arity, count)?;
+        if self.frames.len() >= FRAMES_MAX {
+            return Err(self.error(RuntimeError::StackOverflow));
+        }
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: self.stack.len() - count - 1,
+        });
+        Ok(())
+    }
+
+    fn check_arity(&self, expected: usize, got: usize) -> Result<()> {
+        if expected == got {
+            Ok(())
+        } else {
+            Err(self.error(RuntimeError::Arity { expected, got }))
+        }
+    }
+
+    fn closure(&mut self) {
+        let index = self.read_u16() as usize;
+        let function = self.frame().closure.function.chunk.functions[index].clone();
+        let mut upvalues = Vec::with_capacity(function.upvalue_count);
+        for _ in 0..function.upvalue_count {
+            let is_local = self.read_byte() == 1;
+            let index = self.read_byte() as usize;
+            upvalues.push(if is_local {
+                self.capture_upvalue(self.frame().base + index)
+            } else {
+                self.frame().closure.upvalues[index].clone()
+            });
+        }
+        self.push(LoxObject::VmClosure(Closure { function, upvalues }));
+    }
+
+    fn class(&mut self) -> Result<()> {
+        let name = self.read_string();
+        let method_count = self.read_byte() as usize;
+        let has_superclass = self.read_byte() == 1;
+
+        let methods = self
+            .stack
+            .split_off(self.stack.len() - method_count)
+            .into_iter()
+            .map(|method| match &method {
+                LoxObject::VmClosure(closure) => (closure.function.name.clone(), method),
+                _ => unreachable!("methods are always compiled to closures"),
+            })
+            .collect();
+        let superclass = if has_superclass {
+            match self.pop() {
+                LoxObject::Class(superclass) => Some(superclass),
+                _ => return Err(self.error(RuntimeError::SuperclassNotClass)),
+            }
+        } else {
+            None
+        };
+
+        let class = LoxClass::new(name, superclass, methods);
+        self.push(LoxObject::Class(Rc::new(class)));
+        Ok(())
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let position = self
+            .open_upvalues
+            .iter()
+            .position(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(index) if index >= slot));
+        if let Some(position) = position {
+            let existing = &self.open_upvalues[position];
+            if matches!(*existing.borrow(), Upvalue::Open(index) if index == slot) {
+                return existing.clone();
+            }
+        }
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        let position = position.unwrap_or(self.open_upvalues.len());
+        self.open_upvalues.insert(position, upvalue.clone());
+        upvalue
+    }
+
+    /// Moves every captured variable at or above slot off the stack and into its upvalue
+    fn close_upvalues(&mut self, slot: usize) {
+        let first_open = self
+            .open_upvalues
+            .iter()
+            .position(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(index) if index >= slot))
+            .unwrap_or(self.open_upvalues.len());
+        for upvalue in self.open_upvalues.drain(first_open..) {
+            let mut upvalue = upvalue.borrow_mut();
+            if let Upvalue::Open(index) = *upvalue {
+                *upvalue = Upvalue::Closed(self.stack[index].clone());
+            }
+        }
+    }
+
+    fn binary(&mut self, op: fn(LoxObject, LoxObject) -> Result<LoxObject>) -> Result<()> {
+        let right = self.pop();
+        let left = self.pop();
+        let result = op(left, right).map_err(|e| self.error(e))?;
+        self.push(result);
+        Ok(())
+    }
+
+    // mirrors the tree-walker, ordering values that can't be ordered is an error
+    fn compare(&mut self, op: fn(&LoxObject, &LoxObject) -> bool) -> Result<()> {
+        let right = self.pop();
+        let left = self.pop();
+        if left.partial_cmp(&right).is_none() {
+            return Err(self.error(RuntimeError::InvalidOperand));
+        }
+        self.push(LoxObject::Bool(op(&left, &right)));
+        Ok(())
+    }
+
+    fn error(&self, error: RuntimeError) -> RuntimeError {
+        let trace = self
+            .frames
+            .iter()
+            .rev()
+            .map(|frame| {
+                let function = &frame.closure.function;
+                TraceFrame {
+                    function: (!function.name.is_empty()).then(|| function.name.clone()),
+                    span: function.chunk.span(frame.ip.saturating_sub(1)),
+                }
+            })
+            .collect();
+        RuntimeError::Traceback {
+            error: Box::new(error),
+            trace,
+        }
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames
+            .last()
+            .expect("the script frame is always there while running")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames
+            .last_mut()
+            .expect("the script frame is always there while running")
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame_mut();
+        let byte = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let frame = self.frame_mut();
+        let value = frame.closure.function.chunk.read_u16(frame.ip);
+        frame.ip += 2;
+        value
+    }
+
+    fn read_constant(&mut self) -> &LoxObject {
+        let index = self.read_u16() as usize;
+        &self.frame().closure.function.chunk.constants[index]
+    }
+
+    fn read_string(&mut self) -> SmartString {
+        match self.read_constant() {
+            LoxObject::String(string) => string.clone(),
+            _ => unreachable!("names are always string constants"),
+        }
+    }
+
+    fn push(&mut self, value: LoxObject) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> LoxObject {
+        self.stack
+            .pop()
+            .expect("the compiler keeps the stack balanced")
+    }
+
+    fn peek(&self, distance: usize) -> &LoxObject {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner;
+
+    fn global(source: &str, name: &str) -> LoxObject {
+        let mut vm = Vm::new();
+        vm.eval(source).unwrap();
+        vm.globals[name].clone()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            global("var a = (1 + 2) * 3 - 4 / 2;", "a"),
+            LoxObject::Float(7.0)
+        );
+    }
+
+    #[test]
+    fn evaluate_expression() {
+        let mut vm = Vm::new();
+        vm.eval("var xs = [1, 2];").unwrap();
+        let tokens = scanner::scan("xs[1] * 10").unwrap();
+        let expression = Parser::new(tokens).parse_expression().unwrap();
+        assert_eq!(vm.evaluate(expression).unwrap(), LoxObject::Float(20.0));
+        // the stack is left clean for whatever runs next
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn compile_error_span() {
+        let locals: String = (0..300).map(|i| format!("var a{i};\n")).collect();
+        let Err(Error::CompileError(e)) = Vm::new().eval(&format!("fun f() {{\n{locals}}}")) else {
+            panic!("expected a compile error");
+        };
+        assert!(matches!(e.kind(), CompileError::TooManyLocals));
+        // slot zero holds the function itself, so the 256th variable is one too many
+        assert_eq!(e.span().map(|span| span.line), Some(257));
+    }
+
+    #[test]
+    fn shared_upvalues() {
+        let source = "
+            var get; var set;
+            fun make() { var v = 1; fun g() { return v; } fun s(x) { v = x; } get = g; set = s; }
+            make();
+            set(5);
+            var a = get();
+        ";
+        assert_eq!(global(source, "a"), LoxObject::Float(5.0));
+    }
+
+    #[test]
+    fn inheritance() {
+        let source = "
+            class A { init(n) { this.n = n; } get() { return this.n; } }
+            class B < A { get() { return super.get() * 2; } }
+            var a = B(21).get();
+        ";
+        assert_eq!(global(source, "a"), LoxObject::Float(42.0));
+    }
+
+    #[test]
+    fn loop_jumps() {
+        let source = "
+            var a = 0;
+            for (var i = 0; i < 10; i = i + 1) {
+                if (i == 3) continue;
+                if (i > 6) break;
+                a = a + i;
+            }
+        ";
+        assert_eq!(global(source, "a"), LoxObject::Float(18.0));
+    }
+
+    #[test]
+    fn runtime_error_resets() {
+        let mut vm = Vm::new();
+        assert!(vm.eval("var a = 1; a();").is_err());
+        assert!(vm.stack.is_empty() && vm.frames.is_empty());
+        vm.eval("var b = a + 1;").unwrap();
+        assert_eq!(vm.globals["b"], LoxObject::Float(2.0));
+    }
+}
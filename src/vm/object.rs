@@ -0,0 +1,47 @@
+use super::chunk::Chunk;
+use crate::syntax_trees::lox_object::LoxObject;
+use crate::token::SmartString;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// A compiled function, shared by every closure made from it
+pub struct Function {
+    pub name: SmartString,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
+
+/// A captured variable, it points at the stack slot until that slot goes out of scope and then
+/// holds the value itself
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(LoxObject),
+}
+
+#[derive(Clone)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl fmt::Debug for Closure {
+    // upvalues can hold this closure, so only print the name
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Closure")
+            .field("function", &self.function.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.function.name.is_empty() {
+            write!(f, "<script>")
+        } else {
+            write!(f, "<fn {}>", self.function.name)
+        }
+    }
+}